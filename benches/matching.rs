@@ -0,0 +1,62 @@
+//! Compares testing freshly generated public keys by base58-encoding every
+//! one against ruling most out from their raw bytes first, and computing
+//! public keys one at a time against in batches.
+//!
+//! Run with `cargo bench --bench matching`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_vanity_generator::keygen::{public_key, SeedStream};
+use solana_vanity_generator::{Matcher, VanityPosition, VanityRequest};
+use std::hint::black_box;
+
+fn bench_matching(c: &mut Criterion) {
+    let keys: Vec<Pubkey> = (0..4096).map(|_| Keypair::new().pubkey()).collect();
+    let requests = [
+        ("prefix", VanityRequest::new("abcd", VanityPosition::Prefix)),
+        ("suffix", VanityRequest::new("pump", VanityPosition::Suffix)),
+        (
+            "both",
+            VanityRequest::new("sol", VanityPosition::Both).with_suffix("xyz"),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("matching");
+    group.throughput(Throughput::Elements(keys.len() as u64));
+    for (name, request) in &requests {
+        let matcher = Matcher::new(request).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("encode", name), &keys, |b, keys| {
+            b.iter(|| {
+                keys.iter()
+                    .filter(|key| matcher.is_match(&key.to_string()))
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("prefilter", name), &keys, |b, keys| {
+            b.iter(|| {
+                keys.iter()
+                    .filter(|key| {
+                        matcher.may_match(&key.to_bytes()) && matcher.is_match(&key.to_string())
+                    })
+                    .count()
+            })
+        });
+    }
+    group.finish();
+
+    // Key generation itself, for scale
+    let mut group = c.benchmark_group("keygen");
+    group.bench_function("keypair", |b| b.iter(|| black_box(Keypair::new().pubkey())));
+    let seed = [7u8; 32];
+    group.bench_function("public_key", |b| b.iter(|| public_key(black_box(&seed))));
+    let mut seeds = SeedStream::new();
+    group.bench_function("seed_stream", |b| {
+        b.iter(|| black_box(seeds.next_candidate()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_matching);
+criterion_main!(benches);
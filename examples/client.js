@@ -25,30 +25,51 @@ async function generateVanityAddress(pattern, position) {
   const jobId = data.job_id;
   console.log(`Job started with ID: ${jobId}`);
   
-  // Poll for results
-  return pollForResults(jobId);
+  // Follow the job's events until it finishes
+  return followEvents(jobId);
 }
 
-// Function to poll for job results
-async function pollForResults(jobId) {
-  console.log('Polling for results...');
+// Function to follow a job's server-sent events until it finishes
+async function followEvents(jobId) {
+  console.log('Waiting for results...');
   
-  // Poll every 1 second
-  while (true) {
-    const response = await fetch(`http://localhost:3001/status/${jobId}`);
-    const data = await response.json();
-    
-    if (data.status === 'complete') {
-      console.log('Address found!');
-      return data.result;
-    } else if (data.status === 'running') {
-      console.log('Still searching...');
-      // Wait for 1 second before polling again
-      await new Promise(resolve => setTimeout(resolve, 1000));
-    } else {
-      throw new Error(`Job failed or cancelled: ${JSON.stringify(data)}`);
+  const response = await fetch(`http://localhost:3001/jobs/${jobId}/events`);
+  const decoder = new TextDecoder();
+  let buffer = '';
+  let result = null;
+  
+  // Events are separated by a blank line; a chunk may end mid-event
+  for await (const chunk of response.body) {
+    buffer += decoder.decode(chunk, { stream: true }).replace(/\r/g, '');
+    let end;
+    while ((end = buffer.indexOf('\n\n')) !== -1) {
+      const block = buffer.slice(0, end);
+      buffer = buffer.slice(end + 2);
+      
+      let event = null;
+      let data = '';
+      for (const line of block.split('\n')) {
+        if (line.startsWith('event:')) event = line.slice(6).trim();
+        if (line.startsWith('data:')) data += line.slice(5).trim();
+      }
+      const payload = data ? JSON.parse(data) : {};
+      
+      if (event === 'status' && payload.status === 'pending') {
+        console.log(`Queued at position ${payload.queue_position}...`);
+      } else if (event === 'progress') {
+        console.log(`Still searching... ${payload.attempts} attempts`);
+      } else if (event === 'result') {
+        result = result || payload;
+      } else if (event === 'done') {
+        if (payload.status !== 'complete') {
+          throw new Error(`Job failed or cancelled: ${JSON.stringify(payload)}`);
+        }
+        console.log('Address found!');
+        return result || payload.result;
+      }
     }
   }
+  throw new Error('The event stream ended before the job finished');
 }
 
 // Example usage
@@ -34,35 +34,49 @@ def generate_vanity_address(pattern, position):
     job_id = data["job_id"]
     print(f"Job started with ID: {job_id}")
     
-    # Poll for results
-    return poll_for_results(job_id)
+    # Follow the job's events until it finishes
+    return follow_events(job_id)
 
-def poll_for_results(job_id):
+def follow_events(job_id):
     """
-    Poll for the results of a job.
+    Follow a job's server-sent events until it finishes.
     
     Args:
-        job_id (str): The job ID to poll for
+        job_id (str): The job ID to follow
         
     Returns:
         dict: A dictionary containing the public_key and private_key
     """
-    print("Polling for results...")
+    print("Waiting for results...")
     
-    # Poll every 1 second
-    while True:
-        response = requests.get(f"http://localhost:3001/status/{job_id}")
-        data = response.json()
-        
-        if data.get("status") == "complete":
-            print("Address found!")
-            return data["result"]
-        elif data.get("status") == "running":
-            print("Still searching...")
-            # Wait for 1 second before polling again
-            time.sleep(1)
-        else:
-            raise Exception(f"Job failed or cancelled: {json.dumps(data)}")
+    response = requests.get(
+        f"http://localhost:3001/jobs/{job_id}/events", stream=True
+    )
+    
+    # Each event is an "event:" line and a "data:" line, ended by a blank line
+    event, data, result = None, "", None
+    for line in response.iter_lines(decode_unicode=True):
+        if line.startswith("event:"):
+            event = line[len("event:"):].strip()
+        elif line.startswith("data:"):
+            data += line[len("data:"):].strip()
+        elif line == "" and event:
+            payload = json.loads(data) if data else {}
+            
+            if event == "status" and payload.get("status") == "pending":
+                print(f"Queued at position {payload.get('queue_position')}...")
+            elif event == "progress":
+                print(f"Still searching... {payload.get('attempts')} attempts")
+            elif event == "result":
+                result = result or payload
+            elif event == "done":
+                if payload.get("status") != "complete":
+                    raise Exception(f"Job failed or cancelled: {json.dumps(payload)}")
+                print("Address found!")
+                return result or payload["result"]
+            event, data = None, ""
+    
+    raise Exception("The event stream ended before the job finished")
 
 def main():
     """Main function to demonstrate the API usage"""
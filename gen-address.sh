@@ -34,14 +34,21 @@ if [ -z "$JOB_ID" ]; then
 fi
 
 echo -e "\033[1;32m✅ Job started with ID: $JOB_ID\033[0m"
-echo -e "\033[1;33m⏳ Checking for results...\033[0m"
+echo -e "\033[1;33m⏳ Waiting for results...\033[0m"
 
-# Poll for results with a spinning animation
+# Follow the job's event stream; the server pushes progress about once a second
 SYMBOLS=("⠋" "⠙" "⠹" "⠸" "⠼" "⠴" "⠦" "⠧" "⠇" "⠏")
 COUNTER=0
 START_TIME=$(date +%s)
+EVENT=""
+
+while IFS= read -r LINE; do
+    case "$LINE" in
+        "event: "*) EVENT=${LINE#event: }; continue ;;
+        "data: "*) STATUS_RESPONSE=${LINE#data: } ;;
+        *) continue ;;
+    esac
 
-while true; do
     SYMBOL=${SYMBOLS[$COUNTER]}
     COUNTER=$(( (COUNTER + 1) % 10 ))
     
@@ -49,11 +56,11 @@ while true; do
     CURRENT_TIME=$(date +%s)
     ELAPSED=$((CURRENT_TIME - START_TIME))
     
-    # Check status
-    STATUS_RESPONSE=$(curl -s http://127.0.0.1:3001/status/$JOB_ID)
-    
-    # Display spinner and time
-    echo -ne "\r\033[1;34m$SYMBOL\033[0m Searching... [${ELAPSED}s elapsed]"
+    # Display spinner and time until the final event
+    if [ "$EVENT" != "done" ]; then
+        echo -ne "\r\033[1;34m$SYMBOL\033[0m Searching... [${ELAPSED}s elapsed]"
+        continue
+    fi
     
     # Check if complete
     if echo "$STATUS_RESPONSE" | grep -q '"status":"complete"'; then
@@ -65,15 +72,9 @@ while true; do
         echo -e "  Public key:  \033[0;32m$PUB_KEY\033[0m"
         echo -e "  Private key: \033[0;33m$PRIV_KEY\033[0m"
         echo -e "\n\033[1;31m⚠️  IMPORTANT: Save your private key securely!\033[0m"
-        break
-    fi
-    
-    # Check if error
-    if echo "$STATUS_RESPONSE" | grep -q '"error"'; then
+    else
         ERROR=$(echo $STATUS_RESPONSE | grep -o '"error":"[^"]*' | sed 's/"error":"//')
         echo -e "\r\033[1;31m❌ Error: $ERROR\033[0m                                  "
-        break
     fi
-    
-    sleep 0.1
-done
+    break
+done < <(curl -sN http://127.0.0.1:3001/jobs/$JOB_ID/events)
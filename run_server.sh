@@ -1,4 +1,4 @@
 #!/bin/bash
 cd "$(dirname "$0")"
 echo "Starting Solana Vanity Address Generator Server..."
-cargo run --bin solana-vanity-generator
+cargo run --bin solana-vanity-generator -- "$@"
@@ -1,178 +1,961 @@
+use age::x25519::Identity;
+use clap::{value_t, App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::env;
+use solana_vanity_generator::{
+    find_vanity_addresses, measure_keys_per_second, parse_identities, ApiError, Derivation,
+    DerivedAccountOptions, Estimate, EventParser, JobEventKind, JobStatus, KeyFormat, MatchMode,
+    Matcher, MnemonicOptions, PdaOptions, SearchStats, SecretString, SplitKey, SplitKeyOptions,
+    TokenAccountOptions, TokenProgram, VanityPosition, VanityRequest, VanityResult,
+    MAX_PATTERN_LEN, MIN_PATTERN_LEN,
+};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::time::sleep;
+use zeroize::Zeroizing;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
-
-    // Check for proper usage
-    if args.len() < 3 {
-        println!("{}", "Usage:".yellow().bold());
-        println!("{} <pattern> <position>", args[0].green());
-        println!();
-        println!("Example:");
-        println!("{} abc prefix", args[0].green());
-        println!("{} xyz suffix", args[0].green());
-        println!();
-        println!("Parameters:");
-        println!(
-            "  {} - 3-8 character pattern to search for",
-            "pattern".cyan()
-        );
-        println!("  {} - Either 'prefix' or 'suffix'", "position".cyan());
+    let matches = App::new("vanity")
+        .about("Generate Solana vanity addresses")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .arg(
+            Arg::with_name("pattern")
+                .help("Pattern to search for")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("position")
+                .help("Either 'prefix', 'suffix' or 'both'")
+                .required(true)
+                .index(2),
+        )
+        .args(&request_args())
+        .arg(
+            Arg::with_name("server")
+                .long("server")
+                .value_name("URL")
+                .takes_value(true)
+                .help("Submit the job to a generator server instead of grinding locally"),
+        )
+        .arg(
+            Arg::with_name("outfile")
+                .long("outfile")
+                .short("o")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("Write the keypair to this file, or one file per address into this directory"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["json-array", "base58", "hex"])
+                .help("Private key encoding [default: base58 on screen, json-array in files]"),
+        )
+        .arg(
+            Arg::with_name("identity")
+                .long("identity")
+                .short("i")
+                .value_name("FILE")
+                .takes_value(true)
+                .requires("server")
+                .help("age identity file; the server seals results to its public key"),
+        )
+        .subcommand(
+            SubCommand::with_name("estimate")
+                .about("Estimate how long a pattern will take to find")
+                .arg(
+                    Arg::with_name("pattern")
+                        .help("Pattern to search for")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("position")
+                        .help("Either 'prefix', 'suffix' or 'both'")
+                        .default_value("prefix")
+                        .index(2),
+                )
+                .args(&request_args())
+                .arg(
+                    Arg::with_name("server")
+                        .long("server")
+                        .value_name("URL")
+                        .takes_value(true)
+                        .help("Ask a generator server for its estimate instead of measuring locally"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("split-key")
+                .about("Let someone else search without ever seeing the private key")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("new")
+                        .about("Create a secret and print the public point to search with")
+                        .arg(
+                            Arg::with_name("outfile")
+                                .long("outfile")
+                                .short("o")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Where to keep the secret; it must not exist yet"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("combine")
+                        .about("Add a search's offset to the secret to get the address's key")
+                        .arg(
+                            Arg::with_name("secret")
+                                .help("File written by 'split-key new'")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("offset")
+                                .help("Hex offset from the search result")
+                                .required(true)
+                                .index(2),
+                        )
+                        .arg(
+                            Arg::with_name("address")
+                                .long("address")
+                                .value_name("PUBKEY")
+                                .takes_value(true)
+                                .help("Address the search reported, to check the result against"),
+                        )
+                        .arg(
+                            Arg::with_name("outfile")
+                                .long("outfile")
+                                .short("o")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .help("Write the expanded secret key to this file"),
+                        )
+                        .arg(
+                            Arg::with_name("format")
+                                .long("format")
+                                .value_name("FORMAT")
+                                .takes_value(true)
+                                .possible_values(&["base58", "hex"])
+                                .default_value("base58")
+                                .help("Expanded secret key encoding"),
+                        ),
+                ),
+        )
+        .after_help(
+            "EXAMPLES:\n    vanity abc prefix\n    vanity xyz suffix --server http://127.0.0.1:3001\n    vanity xyz suffix --server http://127.0.0.1:3001 --identity key.txt\n    vanity 'So?ana*' prefix --mode glob\n    vanity ABC both --suffix xyz\n    vanity sol prefix --or moon --or pump\n    vanity abc prefix --outfile id.json\n    vanity abc prefix --mnemonic --words 24\n    vanity abc prefix --pda <PROGRAM_ID> --seed vault\n    vanity abc prefix --base <PUBKEY> --owner Stake11111111111111111111111111111111111111\n    vanity abc prefix --mint EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v\n    vanity abc prefix --split-key <PUBLIC_POINT> --server http://127.0.0.1:3001\n    vanity split-key new --outfile split.json\n    vanity split-key combine split.json <OFFSET>\n    vanity estimate abcdef",
+        )
+        .get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("split-key") {
+        if let Err(e) = split_key(matches) {
+            println!("{} {}", "ERROR:".red().bold(), e);
+        }
         return Ok(());
     }
 
-    let pattern = &args[1];
-    let position = &args[2].to_lowercase();
-
-    // Validate inputs
-    if pattern.len() < 3 || pattern.len() > 8 {
-        println!(
-            "{} Pattern must be between 3-8 characters long",
-            "ERROR:".red().bold()
-        );
+    if let Some(matches) = matches.subcommand_matches("estimate") {
+        let (request, matcher) = match build_request(matches) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("{} {}", "ERROR:".red().bold(), err);
+                return Ok(());
+            }
+        };
+        match estimate(matches.value_of("server"), &request, &matcher).await {
+            Ok(estimate) => print_estimate(&request, &estimate),
+            Err(e) => println!("{} {}", "ERROR:".red().bold(), e),
+        }
         return Ok(());
     }
 
-    if position != "prefix" && position != "suffix" {
-        println!(
-            "{} Position must be 'prefix' or 'suffix'",
-            "ERROR:".red().bold()
-        );
-        return Ok(());
+    let (request, matcher) = match build_request(&matches) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            println!("{} {}", "ERROR:".red().bold(), err);
+            return Ok(());
+        }
+    };
+    let outfile = matches.value_of("outfile").map(PathBuf::from);
+    // Files default to the array solana-keygen writes, the screen to base58
+    let format = match matches.value_of("format") {
+        Some(format) => format.parse::<KeyFormat>()?,
+        None if outfile.is_some() => KeyFormat::JsonArray,
+        None => KeyFormat::Base58,
+    };
+    // Catch a bad destination before grinding, not after
+    if let Some(outfile) = &outfile {
+        let problem = if request.pda.is_some() || request.derived_account.is_some() {
+            Some("Derived addresses have no keypair to write".to_string())
+        } else if request.split_key.is_some() {
+            Some("Split-key results have no keypair; combine the offset with 'vanity split-key combine --outfile'".to_string())
+        } else if outfile.is_dir() {
+            None
+        } else if request.count > 1 {
+            Some("--outfile must be an existing directory when --count is above 1".to_string())
+        } else if outfile.exists() {
+            Some(format!("{} already exists", outfile.display()))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            println!("{} {}", "ERROR:".red().bold(), problem);
+            return Ok(());
+        }
     }
 
-    // Set the server URL
-    let server = "http://127.0.0.1:3001";
+    // Results sealed by the server are opened with these
+    let identities = match matches.value_of("identity").map(read_identities) {
+        Some(Ok(identities)) => identities,
+        Some(Err(err)) => {
+            println!("{} {}", "ERROR:".red().bold(), err);
+            return Ok(());
+        }
+        None => Vec::new(),
+    };
+    let recipient = identities
+        .first()
+        .map(|identity| identity.to_public().to_string());
 
     println!(
-        "{} Generating Solana address with {} '{}'...",
+        "{} Generating Solana address with {}...",
         "⏳".yellow(),
-        position.cyan(),
-        pattern.cyan().bold()
+        request.to_string().cyan().bold()
     );
+    if let Some(expected) = matcher.expected_attempts() {
+        println!("   Expected attempts: ~{:.0}", expected);
+    }
 
-    // Start the job
-    let job_id = match start_job(server, pattern, position).await {
-        Ok(id) => {
-            if id.is_empty() {
-                println!("{} Failed to start generation job", "ERROR:".red().bold());
-                return Ok(());
+    let start_time = Instant::now();
+    let outcome = match matches.value_of("server") {
+        Some(server) => generate_remote(server, &request, recipient, start_time).await,
+        None => generate_local(request, matcher, start_time).await,
+    };
+
+    let results = match outcome.and_then(|results| {
+        results
+            .into_iter()
+            .map(|result| {
+                result
+                    .decrypt_with(&identities)
+                    .and_then(|result| result.with_format(format))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    }) {
+        Ok(results) => results,
+        Err(e) => {
+            println!("\n{} {}", "ERROR:".red().bold(), e);
+            return Ok(());
+        }
+    };
+
+    match &outfile {
+        Some(outfile) => match save_results(&results, outfile) {
+            Ok(paths) => print_saved(&results, &paths, start_time.elapsed().as_secs_f32()),
+            Err(e) => {
+                // Never lose a found key because the file couldn't be written
+                println!("\n{} {}", "ERROR:".red().bold(), e);
+                print_results(&results, start_time.elapsed().as_secs_f32());
             }
-            id
+        },
+        None => print_results(&results, start_time.elapsed().as_secs_f32()),
+    }
+
+    Ok(())
+}
+
+// Options that shape the search, shared by generation and estimates
+fn request_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("or")
+            .long("or")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Alternative pattern searched in the same pass; may be repeated"),
+        Arg::with_name("mode")
+            .long("mode")
+            .value_name("MODE")
+            .takes_value(true)
+            .possible_values(&["exact", "glob", "regex"])
+            .default_value("exact")
+            .help("How to interpret the pattern"),
+        Arg::with_name("suffix")
+            .long("suffix")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .help("Required ending when position is 'both'"),
+        Arg::with_name("case-sensitive")
+            .long("case-sensitive")
+            .help("Match the exact letter case of the pattern"),
+        Arg::with_name("count")
+            .long("count")
+            .value_name("N")
+            .takes_value(true)
+            .default_value("1")
+            .help("Number of matching addresses to generate (at most 1000)"),
+        Arg::with_name("mnemonic")
+            .long("mnemonic")
+            .help("Generate BIP39 seed phrases that wallets can restore (much slower)"),
+        Arg::with_name("words")
+            .long("words")
+            .value_name("N")
+            .takes_value(true)
+            .possible_values(&["12", "24"])
+            .requires("mnemonic")
+            .help("Seed phrase length [default: 12]"),
+        Arg::with_name("passphrase")
+            .long("passphrase")
+            .value_name("PASSPHRASE")
+            .takes_value(true)
+            .requires("mnemonic")
+            .help("BIP39 passphrase protecting the seed phrase"),
+        Arg::with_name("derivation")
+            .long("derivation")
+            .value_name("PATH")
+            .takes_value(true)
+            .possible_values(&["bip44", "bare"])
+            .requires("mnemonic")
+            .help("m/44'/501'/0'/0' as Phantom and Solflare use, or the bare seed as solana-keygen does [default: bip44]"),
+        Arg::with_name("pda")
+            .long("pda")
+            .value_name("PROGRAM_ID")
+            .takes_value(true)
+            .conflicts_with("mnemonic")
+            .help("Search program derived addresses of this program instead of keypairs"),
+        Arg::with_name("seed")
+            .long("seed")
+            .value_name("TEXT")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .requires("pda")
+            .help("Fixed PDA seed before the varied one; may be repeated"),
+        Arg::with_name("variable")
+            .long("variable")
+            .value_name("KIND")
+            .takes_value(true)
+            .possible_values(&["counter", "random"])
+            .requires("pda")
+            .help("Vary a little-endian u64 counter or random bytes as the last PDA seed [default: counter]"),
+        Arg::with_name("seed-len")
+            .long("seed-len")
+            .value_name("BYTES")
+            .takes_value(true)
+            .requires("pda")
+            .help("Length of a random varied PDA seed [default: 8]"),
+        Arg::with_name("base")
+            .long("base")
+            .value_name("PUBKEY")
+            .takes_value(true)
+            .requires("owner")
+            .conflicts_with_all(&["mnemonic", "pda"])
+            .help("Search create_with_seed accounts of this base key instead of keypairs"),
+        Arg::with_name("owner")
+            .long("owner")
+            .value_name("PROGRAM_ID")
+            .takes_value(true)
+            .requires("base")
+            .help("Program that will own the derived account, e.g. the stake program"),
+        Arg::with_name("seed-prefix")
+            .long("seed-prefix")
+            .value_name("TEXT")
+            .takes_value(true)
+            .requires("base")
+            .help("Fixed start of the create_with_seed seed; a counter follows it"),
+        Arg::with_name("mint")
+            .long("mint")
+            .value_name("MINT")
+            .takes_value(true)
+            .conflicts_with_all(&["pda", "base"])
+            .help("Match the wallet's associated token account for this mint instead"),
+        Arg::with_name("token-program")
+            .long("token-program")
+            .value_name("PROGRAM")
+            .takes_value(true)
+            .possible_values(&["token", "token-2022"])
+            .requires("mint")
+            .help("Token program the mint belongs to [default: token]"),
+        Arg::with_name("split-key")
+            .long("split-key")
+            .value_name("PUBLIC_POINT")
+            .takes_value(true)
+            .conflicts_with_all(&["mnemonic", "pda", "base", "mint"])
+            .help("Search offsets to this point from 'vanity split-key new' instead of keypairs"),
+    ]
+}
+
+// Validate the pattern options and compile them for searching
+fn build_request(matches: &ArgMatches) -> Result<(VanityRequest, Matcher), String> {
+    let pattern = matches.value_of("pattern").unwrap();
+    let alternatives: Vec<&str> = matches.values_of("or").into_iter().flatten().collect();
+    let suffix = matches.value_of("suffix");
+    let mode = matches.value_of("mode").unwrap().parse::<MatchMode>()?;
+
+    // Validate inputs
+    if mode == MatchMode::Exact
+        && std::iter::once(pattern)
+            .chain(alternatives.iter().copied())
+            .chain(suffix)
+            .any(|part| part.len() < MIN_PATTERN_LEN || part.len() > MAX_PATTERN_LEN)
+    {
+        return Err(format!(
+            "Pattern must be between {}-{} characters long",
+            MIN_PATTERN_LEN, MAX_PATTERN_LEN
+        ));
+    }
+
+    let position = matches
+        .value_of("position")
+        .unwrap()
+        .parse::<VanityPosition>()?;
+    let mut request = VanityRequest::new(pattern, position)
+        .with_patterns(alternatives)
+        .with_mode(mode)
+        .with_case_sensitive(matches.is_present("case-sensitive"))
+        .with_count(value_t!(matches, "count", usize).unwrap_or_else(|e| e.exit()));
+    request.suffix = suffix.map(str::to_string);
+    if matches.is_present("mnemonic") {
+        let words = value_t!(matches, "words", usize).unwrap_or(12);
+        let derivation = matches
+            .value_of("derivation")
+            .unwrap_or("bip44")
+            .parse::<Derivation>()?;
+        request = request.with_mnemonic(
+            MnemonicOptions::new(words)
+                .with_passphrase(matches.value_of("passphrase").unwrap_or_default())
+                .with_derivation(derivation),
+        );
+    }
+    if let Some(program_id) = matches.value_of("pda") {
+        let mut pda =
+            PdaOptions::new(program_id).with_seeds(matches.values_of("seed").into_iter().flatten());
+        if matches.value_of("variable") == Some("random") {
+            pda = pda.with_random_seed(value_t!(matches, "seed-len", usize).unwrap_or(8));
         }
-        Err(e) => {
-            println!("{} {}", "ERROR:".red().bold(), e);
+        request = request.with_pda(pda);
+    }
+    if let (Some(base), Some(owner)) = (matches.value_of("base"), matches.value_of("owner")) {
+        request = request.with_derived_account(
+            DerivedAccountOptions::new(base, owner)
+                .with_seed_prefix(matches.value_of("seed-prefix").unwrap_or_default()),
+        );
+    }
+    if let Some(mint) = matches.value_of("mint") {
+        let token_program = matches
+            .value_of("token-program")
+            .unwrap_or("token")
+            .parse::<TokenProgram>()?;
+        request = request
+            .with_token_account(TokenAccountOptions::new(mint).with_token_program(token_program));
+    }
+    if let Some(public_point) = matches.value_of("split-key") {
+        request = request.with_split_key(SplitKeyOptions::new(public_point));
+    }
+    let matcher = Matcher::new(&request)?;
+
+    Ok((request, matcher))
+}
+
+// Work out the odds, from a server's figures or by timing local key generation
+async fn estimate(
+    server: Option<&str>,
+    request: &VanityRequest,
+    matcher: &Matcher,
+) -> Result<Estimate, String> {
+    if let Some(server) = server {
+        return fetch_estimate(server, request).await;
+    }
+
+    println!("{} Measuring local hash rate...", "⏳".yellow());
+    let sample = request.clone();
+    let keys_per_second = tokio::task::spawn_blocking(move || {
+        measure_keys_per_second(&sample, Duration::from_secs(2))
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(Estimate::new(matcher, request.count, keys_per_second))
+}
+
+fn print_estimate(request: &VanityRequest, estimate: &Estimate) {
+    println!(
+        "\n{} Estimate for {}",
+        "📊".green(),
+        request.to_string().cyan().bold()
+    );
+    println!(
+        "   Hash rate:         {:.0} keys/s",
+        estimate.keys_per_second
+    );
+
+    let Some(expected) = estimate.expected_attempts else {
+        println!("   Glob and regex patterns can't be estimated");
+        return;
+    };
+    if expected.is_infinite() {
+        println!("   This pattern can never match");
+        return;
+    }
+    println!("   Expected attempts: ~{:.0} per address", expected);
+
+    if let Some(seconds) = estimate.expected_seconds {
+        println!("   Expected time:     ~{}", format_duration(seconds).bold());
+        // A single match arrives after an exponentially distributed wait
+        if estimate.count == 1 {
             println!(
-                "Is the server running? Start it with {}",
-                "./run_server.sh".green()
+                "   50% chance within {}, 90% within {}",
+                format_duration(seconds * 2f64.ln()),
+                format_duration(seconds * 10f64.ln())
             );
-            return Ok(());
         }
-    };
+    }
+}
 
-    // Track time
-    let start_time = Instant::now();
-    let mut dots = 0;
+// Grind on all local cores without touching the network
+async fn generate_local(
+    request: VanityRequest,
+    matcher: Matcher,
+    start_time: Instant,
+) -> Result<Vec<VanityResult>, String> {
+    let requested = request.count;
+    let expected_attempts = matcher.expected_attempts();
+    let found = Arc::new(AtomicUsize::new(0));
+    let attempts = Arc::new(AtomicU64::new(0));
+    let worker_found = found.clone();
+    let worker_attempts = attempts.clone();
+    let mut search = tokio::task::spawn_blocking(move || {
+        find_vanity_addresses(&request, &AtomicBool::new(false), &worker_attempts, |_| {
+            worker_found.fetch_add(1, Ordering::Relaxed);
+        })
+    });
 
-    // Poll for results
+    let mut dots = 0;
     loop {
-        match check_job_status(server, &job_id).await {
-            Ok((status, result)) => {
-                if status == "complete" {
-                    if let Some((pub_key, priv_key)) = result {
-                        let elapsed = start_time.elapsed().as_secs_f32();
-                        println!(
-                            "\n{} Address found in {:.2} seconds!",
-                            "✅".green(),
-                            elapsed
-                        );
-                        println!("\n{}", "PUBLIC KEY:".green().bold());
-                        println!("{}", pub_key);
-                        println!("\n{}", "PRIVATE KEY:".yellow().bold());
-                        println!("{}", priv_key);
-                        println!(
-                            "\n{}",
-                            "⚠️  IMPORTANT: Save your private key securely! ⚠️"
-                                .red()
-                                .bold()
-                        );
-                        break;
-                    }
-                } else if status == "error" {
-                    println!("\n{} Error checking job status", "ERROR:".red().bold());
-                    break;
+        tokio::select! {
+            joined = &mut search => {
+                let keypairs = joined.map_err(|e| e.to_string())??;
+                return Ok(keypairs
+                    .iter()
+                    .map(|keypair| VanityResult::from(keypair).with_match(&matcher))
+                    .collect());
+            }
+            _ = sleep(Duration::from_millis(500)) => {
+                let found = found.load(Ordering::Relaxed);
+                let stats = SearchStats::new(
+                    attempts.load(Ordering::Relaxed),
+                    start_time.elapsed(),
+                    expected_attempts,
+                    requested - found,
+                );
+                print_progress(start_time, dots, found, requested, &stats);
+                dots += 1;
+            }
+        }
+    }
+}
+
+// Submit the job to a generator server and follow its events until it finishes
+async fn generate_remote(
+    server: &str,
+    request: &VanityRequest,
+    recipient: Option<String>,
+    start_time: Instant,
+) -> Result<Vec<VanityResult>, String> {
+    // Start the job
+    let job_id = start_job(server, request, recipient).await?;
+
+    let mut events = reqwest::Client::new()
+        .get(format!("{}/jobs/{}/events", server, job_id))
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| e.to_string())?;
+    let mut parser = EventParser::new();
+    let mut dots = 0;
+
+    // The server pushes progress about once a second and the results at the end
+    while let Some(chunk) = events
+        .chunk()
+        .await
+        .map_err(|e| format!("Lost the connection to the server: {}", e))?
+    {
+        for event in parser.feed(&chunk) {
+            if event.kind == JobEventKind::Result {
+                continue; // All results come again with "done"
+            }
+            let status: JobStatus = event.parse()?;
+
+            if event.kind == JobEventKind::Done {
+                let error = status.error.clone();
+                let results = match status.status.as_str() {
+                    "complete" => status.into_results(),
+                    _ => Vec::new(),
+                };
+                return if results.is_empty() {
+                    Err(error.unwrap_or_else(|| "The job failed".to_string()))
                 } else {
-                    // Show progress indicator
-                    print!(
-                        "\r{} Searching{} elapsed: {:.1}s",
-                        "⏳".yellow(),
-                        ".".repeat(dots % 4 + 1),
-                        start_time.elapsed().as_secs_f32()
-                    );
-                    dots += 1;
-                    // Flush stdout to make sure the progress shows immediately
-                    use std::io::Write;
-                    std::io::stdout().flush().unwrap();
-                }
+                    Ok(results)
+                };
+            } else if let Some(position) = status.queue_position {
+                print!(
+                    "\r{} Queued on the server, position {}{:<20}",
+                    "⏳".yellow(),
+                    position,
+                    ""
+                );
+                std::io::stdout().flush().unwrap();
+            } else {
+                print_progress(start_time, dots, status.found, request.count, &status.stats);
+                dots += 1;
             }
-            Err(e) => {
-                println!("\n{} {}", "ERROR:".red().bold(), e);
-                break;
+        }
+    }
+    Err("The server closed the job's event stream".to_string())
+}
+
+fn print_progress(
+    start_time: Instant,
+    dots: usize,
+    found: usize,
+    requested: usize,
+    stats: &SearchStats,
+) {
+    let counter = if requested > 1 {
+        format!(" found: {}/{}", found, requested)
+    } else {
+        String::new()
+    };
+    let eta = match stats.eta_seconds {
+        Some(eta) => format!(" ETA: ~{}", format_duration(eta)),
+        None => String::new(),
+    };
+    print!(
+        "\r{} Searching{:<4} elapsed: {:.1}s attempts: {} ({:.0} keys/s){}{}   ",
+        "⏳".yellow(),
+        ".".repeat(dots % 4 + 1),
+        start_time.elapsed().as_secs_f32(),
+        stats.attempts,
+        stats.keys_per_second,
+        eta,
+        counter
+    );
+    // Flush stdout to make sure the progress shows immediately
+    std::io::stdout().flush().unwrap();
+}
+
+// Render a number of seconds as its two most significant units, e.g. "3m 12s"
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        3600..=86399 => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+        86400..=31535999 => format!("{}d {}h", seconds / 86400, seconds % 86400 / 3600),
+        _ => format!("{:.1} years", seconds as f64 / 31536000.0),
+    }
+}
+
+fn print_results(results: &[VanityResult], elapsed: f32) {
+    if results.len() == 1 {
+        println!(
+            "\n{} Address found in {:.2} seconds!",
+            "✅".green(),
+            elapsed
+        );
+    } else {
+        println!(
+            "\n{} {} addresses found in {:.2} seconds!",
+            "✅".green(),
+            results.len(),
+            elapsed
+        );
+    }
+
+    for (i, result) in results.iter().enumerate() {
+        if results.len() > 1 {
+            println!(
+                "\n{}",
+                format!("── Address {} of {} ──", i + 1, results.len()).bold()
+            );
+        }
+        if let Some(pattern) = &result.matched_pattern {
+            println!(
+                "{} Matched pattern '{}'",
+                "🎯".green(),
+                pattern.cyan().bold()
+            );
+        }
+        if let Some(bump) = result.bump {
+            // Program derived addresses have seeds instead of a private key
+            println!("\n{}", "PROGRAM ADDRESS:".green().bold());
+            println!("{}", result.public_key);
+            println!("\n{}", "SEEDS (hex):".yellow().bold());
+            for seed in &result.seeds {
+                println!("{}", seed);
             }
+            if let Some(counter) = result.counter {
+                println!("(last seed is counter {} as u64 little-endian)", counter);
+            }
+            println!("\n{} {}", "BUMP:".yellow().bold(), bump);
+            continue;
+        }
+        if let Some(seed) = &result.seed {
+            // Accounts derived with create_with_seed are signed for by the base key
+            println!("\n{}", "DERIVED ADDRESS:".green().bold());
+            println!("{}", result.public_key);
+            println!("\n{} {}", "SEED:".yellow().bold(), seed);
+            continue;
+        }
+        if let Some(offset) = &result.offset {
+            // Only the holder of the split key's secret can turn this into a key
+            println!("\n{}", "ADDRESS:".green().bold());
+            println!("{}", result.public_key);
+            println!("\n{}", "OFFSET:".yellow().bold());
+            println!("{}", offset);
+            println!(
+                "(combine it with your secret: vanity split-key combine <SECRET_FILE> {} --address {})",
+                offset, result.public_key
+            );
+            continue;
+        }
+        if let Some(token_account) = &result.token_account {
+            // The pattern is in the token account; the keypair is its owner's
+            println!("\n{}", "TOKEN ACCOUNT:".green().bold());
+            println!("{}", token_account);
+            println!("\n{}", "OWNER PUBLIC KEY:".green().bold());
+        } else {
+            println!("\n{}", "PUBLIC KEY:".green().bold());
         }
+        println!("{}", result.public_key);
+        println!("\n{}", "PRIVATE KEY:".yellow().bold());
+        println!("{}", result.private_key.expose());
+        if let Some(mnemonic) = &result.mnemonic {
+            println!("\n{}", "SEED PHRASE:".yellow().bold());
+            println!("{}", mnemonic.expose());
+        }
+    }
 
-        sleep(Duration::from_millis(500)).await;
+    if results.iter().any(|result| !result.private_key.is_empty()) {
+        println!(
+            "\n{}",
+            "⚠️  IMPORTANT: Save your private key securely! ⚠️"
+                .red()
+                .bold()
+        );
     }
+}
 
+fn read_identities(path: &str) -> Result<Vec<Identity>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map(Zeroizing::new)
+        .map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+    parse_identities(&contents)
+}
+
+// The client's half of a split-key search, as 'split-key new' writes it
+#[derive(Serialize, Deserialize)]
+struct SplitKeyFile {
+    public_point: String,
+    secret: SecretString, // Hex scalar and nonce prefix
+}
+
+fn split_key(matches: &ArgMatches) -> Result<(), String> {
+    if let Some(matches) = matches.subcommand_matches("new") {
+        let path = Path::new(matches.value_of("outfile").unwrap());
+        let key = SplitKey::generate();
+        let file = SplitKeyFile {
+            public_point: key.public_point(),
+            secret: KeyFormat::Hex.encode(key.to_bytes().as_ref()).into(),
+        };
+        let contents = Zeroizing::new(serde_json::to_string_pretty(&file).unwrap());
+        write_key_file(path, &contents)
+            .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+
+        println!("{} Secret saved to {}", "🔑".green(), path.display());
+        println!("\n{}", "PUBLIC POINT:".green().bold());
+        println!("{}", file.public_point);
+        println!(
+            "\nShare only the public point, e.g. vanity abc prefix --split-key {} --server <URL>",
+            file.public_point
+        );
+    } else if let Some(matches) = matches.subcommand_matches("combine") {
+        let path = Path::new(matches.value_of("secret").unwrap());
+        let contents = std::fs::read_to_string(path)
+            .map(Zeroizing::new)
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let file: SplitKeyFile = serde_json::from_str(&contents)
+            .map_err(|e| format!("{} is not a split key secret: {}", path.display(), e))?;
+        let key = SplitKey::from_bytes(&Zeroizing::new(
+            KeyFormat::Hex.decode(file.secret.expose())?,
+        ))?;
+        let combined = key.combine(matches.value_of("offset").unwrap())?;
+
+        if let Some(address) = matches.value_of("address") {
+            if address != combined.address().to_string() {
+                return Err(format!(
+                    "The offset gives {} instead of {}; was it found for this secret?",
+                    combined.address(),
+                    address
+                ));
+            }
+        }
+        let format = matches.value_of("format").unwrap().parse::<KeyFormat>()?;
+        let secret = SecretString::from(format.encode(combined.to_bytes().as_ref()));
+
+        println!("{}", "ADDRESS:".green().bold());
+        println!("{}", combined.address());
+        match matches.value_of("outfile") {
+            Some(outfile) => {
+                write_key_file(Path::new(outfile), secret.expose())
+                    .map_err(|e| format!("Couldn't write {}: {}", outfile, e))?;
+                println!(
+                    "\n{} Expanded secret key saved to {}",
+                    "🔑".green(),
+                    outfile
+                );
+            }
+            None => {
+                println!("\n{}", "EXPANDED SECRET KEY:".yellow().bold());
+                println!("{}", secret.expose());
+            }
+        }
+        println!(
+            "\n{}",
+            "⚠️  This is a raw signing key, not a seed; wallets can't import it. ⚠️"
+                .red()
+                .bold()
+        );
+    }
     Ok(())
 }
 
-async fn start_job(server: &str, pattern: &str, position: &str) -> Result<String, reqwest::Error> {
+// Write each private key to its own file, readable only by the current user.
+// A directory gets one `<address>.json` (or `.txt`) file per result.
+fn save_results(results: &[VanityResult], outfile: &Path) -> Result<Vec<PathBuf>, String> {
+    results
+        .iter()
+        .map(|result| {
+            let path = if outfile.is_dir() {
+                let extension = match result.format {
+                    KeyFormat::JsonArray => "json",
+                    _ => "txt",
+                };
+                outfile.join(format!("{}.{}", result.public_key, extension))
+            } else {
+                outfile.to_path_buf()
+            };
+            write_key_file(&path, result.private_key.expose())
+                .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn write_key_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    // Refuse to overwrite an existing keypair
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+fn print_saved(results: &[VanityResult], paths: &[PathBuf], elapsed: f32) {
+    println!(
+        "\n{} {} found in {:.2} seconds!",
+        "✅".green(),
+        if results.len() == 1 {
+            "Address".to_string()
+        } else {
+            format!("{} addresses", results.len())
+        },
+        elapsed
+    );
+    for (result, path) in results.iter().zip(paths) {
+        println!(
+            "{} {} → {}",
+            "🔑".green(),
+            result.public_key.bold(),
+            path.display()
+        );
+        if let Some(token_account) = &result.token_account {
+            println!("   Token account: {}", token_account);
+        }
+        // Key files hold only the keypair, so the phrase has to be shown
+        if let Some(mnemonic) = &result.mnemonic {
+            println!("   Seed phrase: {}", mnemonic.expose());
+        }
+    }
+}
+
+async fn start_job(
+    server: &str,
+    request: &VanityRequest,
+    recipient: Option<String>,
+) -> Result<String, String> {
+    let mut body = serde_json::to_value(request).unwrap();
+    if let Some(recipient) = recipient {
+        body["recipient"] = Value::String(recipient);
+    }
+
     let client = reqwest::Client::new();
     let res = client
         .post(format!("{}/generate", server))
-        .json(&serde_json::json!({
-            "pattern": pattern,
-            "position": position,
-        }))
+        .json(&body)
         .send()
-        .await?;
+        .await
+        .map_err(|e| {
+            format!(
+                "{}\nIs the server running? Start it with {}",
+                e,
+                "./run_server.sh".green()
+            )
+        })?;
 
-    let json: Value = res.json().await?;
-    Ok(json["job_id"].as_str().unwrap_or("").to_string())
+    // A rejected pattern or a full queue says why in the body
+    if !res.status().is_success() {
+        let status = res.status();
+        return Err(match res.json::<ApiError>().await {
+            Ok(error) => error.describe(),
+            Err(_) => format!("The server refused the job ({})", status),
+        });
+    }
+    let json: Value = res.json().await.map_err(|e| e.to_string())?;
+    match json["job_id"].as_str() {
+        Some(job_id) if !job_id.is_empty() => Ok(job_id.to_string()),
+        _ => Err("Failed to start generation job".to_string()),
+    }
 }
 
-async fn check_job_status(
-    server: &str,
-    job_id: &str,
-) -> Result<(String, Option<(String, String)>), reqwest::Error> {
+async fn fetch_estimate(server: &str, request: &VanityRequest) -> Result<Estimate, String> {
     let client = reqwest::Client::new();
     let res = client
-        .get(format!("{}/status/{}", server, job_id))
+        .post(format!("{}/estimate", server))
+        .json(request)
         .send()
-        .await?;
-
-    let json: Value = res.json().await?;
+        .await
+        .map_err(|e| e.to_string())?;
 
-    if let Some(status) = json.get("status") {
-        let status_str = status.as_str().unwrap_or("");
-
-        if status_str == "complete" {
-            if let Some(result) = json.get("result") {
-                let pub_key = result["public_key"].as_str().unwrap_or("").to_string();
-                let priv_key = result["private_key"].as_str().unwrap_or("").to_string();
-                return Ok((status_str.to_string(), Some((pub_key, priv_key))));
-            }
-        }
-
-        return Ok((status_str.to_string(), None));
+    if !res.status().is_success() {
+        let status = res.status();
+        return Err(match res.json::<ApiError>().await {
+            Ok(error) => error.describe(),
+            Err(_) => format!("The server couldn't estimate the pattern ({})", status),
+        });
     }
-
-    Ok(("error".to_string(), None))
+    res.json::<Estimate>().await.map_err(|e| e.to_string())
 }
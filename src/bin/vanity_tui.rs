@@ -1,11 +1,16 @@
 use cursive::align::HAlign;
+use cursive::event::Event;
 use cursive::traits::*;
-use cursive::views::{Dialog, EditView, LinearLayout, ProgressBar, RadioGroup, TextView};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, ProgressBar, RadioGroup, TextView};
 use cursive::{Cursive, CursiveExt};
 use serde_json::Value;
+use solana_vanity_generator::{
+    ApiError, EventParser, JobEventKind, JobStatus, MatchMode, SearchStats, VanityPosition,
+    VanityRequest, MAX_PATTERN_LEN, MIN_PATTERN_LEN,
+};
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 fn main() {
     // Create a basic Cursive app
@@ -43,10 +48,13 @@ fn main() {
 fn main_form(siv: &mut Cursive) {
     // Dialog for input parameters
     let mut position_group = RadioGroup::new();
+    let mut mode_group = RadioGroup::new();
     siv.add_layer(
         Dialog::around(
             LinearLayout::vertical()
-                .child(TextView::new("Enter the pattern to search for:"))
+                .child(TextView::new(
+                    "Enter the pattern to search for\n(separate alternatives with spaces):",
+                ))
                 .child(
                     EditView::new()
                         .content("abc")
@@ -56,9 +64,39 @@ fn main_form(siv: &mut Cursive) {
                 .child(TextView::new("\nSelect position:"))
                 .child(
                     LinearLayout::horizontal()
-                        .child(position_group.button_str("Prefix"))
+                        .child(position_group.button(VanityPosition::Prefix, "Prefix"))
                         .child(TextView::new(" "))
-                        .child(position_group.button_str("Suffix")),
+                        .child(position_group.button(VanityPosition::Suffix, "Suffix"))
+                        .child(TextView::new(" "))
+                        .child(position_group.button(VanityPosition::Both, "Both")),
+                )
+                .child(TextView::new("\nSuffix (only used with Both):"))
+                .child(
+                    EditView::new()
+                        .content("xyz")
+                        .with_name("suffix")
+                        .fixed_width(20),
+                )
+                .child(TextView::new("\nMatch mode:"))
+                .child(
+                    LinearLayout::horizontal()
+                        .child(mode_group.button(MatchMode::Exact, "Exact"))
+                        .child(TextView::new(" "))
+                        .child(mode_group.button(MatchMode::Glob, "Glob (? *)"))
+                        .child(TextView::new(" "))
+                        .child(mode_group.button(MatchMode::Regex, "Regex")),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(Checkbox::new().with_name("case_sensitive"))
+                        .child(TextView::new(" Case-sensitive")),
+                )
+                .child(TextView::new("\nNumber of addresses:"))
+                .child(
+                    EditView::new()
+                        .content("1")
+                        .with_name("count")
+                        .fixed_width(10),
                 )
                 .child(TextView::new("\nServer Address (default is fine):"))
                 .child(
@@ -74,10 +112,23 @@ fn main_form(siv: &mut Cursive) {
                 .call_on_name("pattern", |view: &mut EditView| view.get_content())
                 .unwrap();
 
-            let position = if position_group.selected_id() == 0 {
-                "prefix"
-            } else {
-                "suffix"
+            let position = *position_group.selection();
+            let suffix = s
+                .call_on_name("suffix", |view: &mut EditView| view.get_content())
+                .unwrap();
+            let suffix = (position == VanityPosition::Both).then(|| suffix.to_string());
+
+            let mode = *mode_group.selection();
+            let case_sensitive = s
+                .call_on_name("case_sensitive", |view: &mut Checkbox| view.is_checked())
+                .unwrap();
+
+            let count = s
+                .call_on_name("count", |view: &mut EditView| view.get_content())
+                .unwrap();
+            let Ok(count) = count.trim().parse::<usize>() else {
+                s.add_layer(Dialog::info("Number of addresses must be a whole number."));
+                return;
             };
 
             let server = s
@@ -85,20 +136,39 @@ fn main_form(siv: &mut Cursive) {
                 .unwrap();
 
             // Check that pattern is valid
-            if pattern.len() < 3 || pattern.len() > 8 {
-                s.add_layer(Dialog::info(
-                    "Pattern must be between 3 and 8 characters long.",
-                ));
+            let mut candidates = pattern.split_whitespace();
+            let Some(first) = candidates.next() else {
+                s.add_layer(Dialog::info("Pattern cannot be empty."));
+                return;
+            };
+            let alternatives: Vec<&str> = candidates.collect();
+
+            if mode == MatchMode::Exact
+                && std::iter::once(first)
+                    .chain(alternatives.iter().copied())
+                    .chain(suffix.as_deref())
+                    .any(|part| part.len() < MIN_PATTERN_LEN || part.len() > MAX_PATTERN_LEN)
+            {
+                s.add_layer(Dialog::info(format!(
+                    "Pattern must be between {} and {} characters long.",
+                    MIN_PATTERN_LEN, MAX_PATTERN_LEN
+                )));
+                return;
+            }
+
+            let mut request = VanityRequest::new(first, position)
+                .with_patterns(alternatives)
+                .with_mode(mode)
+                .with_case_sensitive(case_sensitive)
+                .with_count(count);
+            request.suffix = suffix;
+            if let Err(err) = request.validate() {
+                s.add_layer(Dialog::info(err));
                 return;
             }
 
             // Launch the generation process
-            generate_address(
-                s,
-                server.to_string(),
-                pattern.to_string(),
-                position.to_string(),
-            );
+            generate_address(s, server.to_string(), request);
         })
         .button("Cancel", |s| {
             s.pop_layer();
@@ -106,62 +176,90 @@ fn main_form(siv: &mut Cursive) {
     );
 }
 
-async fn start_job(
-    server: String,
-    pattern: String,
-    position: String,
-) -> Result<String, reqwest::Error> {
+async fn start_job(server: String, request: VanityRequest) -> Result<String, String> {
     let client = reqwest::Client::new();
     let res = client
         .post(format!("{}/generate", server))
-        .json(&serde_json::json!({
-            "pattern": pattern,
-            "position": position,
-        }))
+        .json(&request)
         .send()
-        .await?;
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let json: Value = res.json().await?;
-    Ok(json["job_id"].as_str().unwrap_or("").to_string())
+    // A rejected pattern or a full queue says why in the body
+    if !res.status().is_success() {
+        let status = res.status();
+        return Err(match res.json::<ApiError>().await {
+            Ok(error) => error.describe(),
+            Err(_) => format!("The server refused the job ({})", status),
+        });
+    }
+    let json: Value = res.json().await.map_err(|e| e.to_string())?;
+    match json["job_id"].as_str() {
+        Some(job_id) if !job_id.is_empty() => Ok(job_id.to_string()),
+        _ => Err("Failed to start job".to_string()),
+    }
 }
 
-async fn check_job_status(
-    server: String,
-    job_id: String,
-) -> Result<(String, Option<(String, String)>), reqwest::Error> {
-    let client = reqwest::Client::new();
-    let res = client
-        .get(format!("{}/status/{}", server, job_id))
+// Open the job's event stream, which pushes what /status would report
+async fn job_events(server: &str, job_id: &str) -> Result<reqwest::Response, reqwest::Error> {
+    reqwest::Client::new()
+        .get(format!("{}/jobs/{}/events", server, job_id))
         .send()
-        .await?;
-
-    let json: Value = res.json().await?;
+        .await?
+        .error_for_status()
+}
 
-    if let Some(status) = json.get("status") {
-        let status_str = status.as_str().unwrap_or("");
+// Where a running job stands, as reported by /status
+#[derive(Default)]
+struct JobProgress {
+    found: usize,
+    requested: usize,
+    queue_position: Option<usize>, // While the server has the job queued
+    stats: SearchStats,
+}
 
-        if status_str == "complete" {
-            if let Some(result) = json.get("result") {
-                let pub_key = result["public_key"].as_str().unwrap_or("").to_string();
-                let priv_key = result["private_key"].as_str().unwrap_or("").to_string();
-                return Ok((status_str.to_string(), Some((pub_key, priv_key))));
-            }
+impl JobProgress {
+    // Percentage for the progress bar. Without a match to show for it, the
+    // attempts spent so far are measured against the expected total, held
+    // below 100 until the job actually completes.
+    fn percent(&self) -> usize {
+        let requested = self.requested.max(1) as f64;
+        let mut fraction = self.found as f64 / requested;
+        if let Some(expected) = self.stats.expected_attempts {
+            fraction = fraction.max(self.stats.attempts as f64 / (expected * requested));
         }
-
-        return Ok((status_str.to_string(), None));
+        (fraction.min(0.99) * 100.0) as usize
     }
 
-    Ok(("error".to_string(), None))
+    fn describe(&self, elapsed: f32) -> String {
+        if let Some(position) = self.queue_position {
+            return format!(
+                "Queued on the server, position {}\n{:.2}s elapsed",
+                position, elapsed
+            );
+        }
+        let mut message = format!(
+            "Running... {:.2}s elapsed\n{} attempts at {:.0} keys/s",
+            elapsed, self.stats.attempts, self.stats.keys_per_second
+        );
+        if let Some(eta) = self.stats.eta_seconds {
+            message.push_str(&format!("\nETA: ~{:.0}s", eta));
+        }
+        if self.requested > 1 {
+            message.push_str(&format!("\nFound {} of {}", self.found, self.requested));
+        }
+        message
+    }
 }
 
-fn generate_address(siv: &mut Cursive, server: String, pattern: String, position: String) {
+fn generate_address(siv: &mut Cursive, server: String, request: VanityRequest) {
     // Create a progress dialog
     siv.add_layer(
         Dialog::around(
             LinearLayout::vertical()
                 .child(TextView::new(format!(
-                    "Generating address with {} '{}'...",
-                    position, pattern
+                    "Generating address with {}...",
+                    request
                 )))
                 .child(TextView::new(""))
                 .child(ProgressBar::new().with_name("progress"))
@@ -180,8 +278,6 @@ fn generate_address(siv: &mut Cursive, server: String, pattern: String, position
 
     // Clone values for the thread
     let server_clone = server.clone();
-    let pattern_clone = pattern.clone();
-    let position_clone = position.clone();
 
     // Create a thread to do the actual generation
     thread::spawn(move || {
@@ -190,68 +286,88 @@ fn generate_address(siv: &mut Cursive, server: String, pattern: String, position
 
         rt.block_on(async {
             // Start the job
-            match start_job(server_clone.clone(), pattern_clone, position_clone).await {
+            match start_job(server_clone.clone(), request).await {
                 Ok(job_id) => {
-                    if job_id.is_empty() {
+                    let start_time = Instant::now();
+                    let fail = |message: String| {
                         sender
-                            .send(("error".to_string(), "Failed to start job".to_string(), None))
+                            .send(("error".to_string(), message, 0, Vec::new()))
                             .unwrap();
-                        return;
-                    }
+                    };
+                    let mut events = match job_events(&server_clone, &job_id).await {
+                        Ok(events) => events,
+                        Err(e) => return fail(format!("Error: {}", e)),
+                    };
+                    let mut parser = EventParser::new();
 
-                    let start_time = Instant::now();
-
-                    // Poll for results
+                    // Follow the events the server pushes until the job is done
                     loop {
-                        match check_job_status(server_clone.clone(), job_id.clone()).await {
-                            Ok((status, result)) => {
-                                if status == "complete" {
-                                    if let Some((pub_key, priv_key)) = result {
-                                        let elapsed = start_time.elapsed().as_secs_f32();
-                                        sender
-                                            .send((
-                                                "complete".to_string(),
-                                                format!("Found in {:.2}s", elapsed),
-                                                Some((pub_key, priv_key)),
-                                            ))
-                                            .unwrap();
-                                        break;
-                                    }
-                                } else if status == "error" {
-                                    sender
-                                        .send((
-                                            "error".to_string(),
-                                            "Error checking job status".to_string(),
-                                            None,
-                                        ))
-                                        .unwrap();
-                                    break;
+                        let chunk = match events.chunk().await {
+                            Ok(Some(chunk)) => chunk,
+                            Ok(None) => {
+                                return fail("The server closed the event stream".to_string())
+                            }
+                            Err(e) => return fail(format!("Error: {}", e)),
+                        };
+                        for event in parser.feed(&chunk) {
+                            if event.kind == JobEventKind::Result {
+                                continue; // All results come again with "done"
+                            }
+                            let Ok(status) = event.parse::<JobStatus>() else {
+                                continue;
+                            };
+
+                            if event.kind == JobEventKind::Done {
+                                let error = status.error.clone();
+                                let results = if status.status == "complete" {
+                                    status.into_results()
                                 } else {
-                                    // Still running - update with time elapsed
+                                    Vec::new()
+                                };
+                                if !results.is_empty() {
                                     let elapsed = start_time.elapsed().as_secs_f32();
+                                    let message = if results.len() == 1 {
+                                        format!("Found in {:.2}s", elapsed)
+                                    } else {
+                                        format!(
+                                            "Found {} addresses in {:.2}s",
+                                            results.len(),
+                                            elapsed
+                                        )
+                                    };
                                     sender
-                                        .send((
-                                            "running".to_string(),
-                                            format!("Running... {:.2}s elapsed", elapsed),
-                                            None,
-                                        ))
+                                        .send(("complete".to_string(), message, 100, results))
                                         .unwrap();
+                                } else {
+                                    fail(error.unwrap_or_else(|| "The job failed".to_string()));
                                 }
+                                return;
                             }
-                            Err(e) => {
-                                sender
-                                    .send(("error".to_string(), format!("Error: {}", e), None))
-                                    .unwrap();
-                                break;
+
+                            // Still queued or running - update with the attempt statistics
+                            let progress = JobProgress {
+                                found: status.found,
+                                requested: status.requested,
+                                queue_position: status.queue_position,
+                                stats: status.stats,
+                            };
+                            let elapsed = start_time.elapsed().as_secs_f32();
+                            let update = (
+                                "running".to_string(),
+                                progress.describe(elapsed),
+                                progress.percent(),
+                                Vec::new(),
+                            );
+                            // Nobody is listening once the dialog is gone
+                            if sender.send(update).is_err() {
+                                return;
                             }
                         }
-
-                        tokio::time::sleep(Duration::from_millis(500)).await;
                     }
                 }
                 Err(e) => {
                     sender
-                        .send(("error".to_string(), format!("Error: {}", e), None))
+                        .send(("error".to_string(), format!("Error: {}", e), 0, Vec::new()))
                         .unwrap();
                 }
             }
@@ -262,70 +378,108 @@ fn generate_address(siv: &mut Cursive, server: String, pattern: String, position
     siv.set_autorefresh(true);
     siv.add_global_callback('q', move |s| s.quit());
 
-    // Set up a callback to check for messages from the thread
-    siv.cb_sink()
-        .send(Box::new(move |s| {
-            if let Ok((status, message, keys)) = receiver.try_recv() {
-                if status == "complete" {
-                    if let Some((pub_key, priv_key)) = keys {
-                        // Found a match! Show the results
-                        s.pop_layer(); // Remove progress dialog
-                        s.add_layer(
-                            Dialog::around(
-                                LinearLayout::vertical()
-                                    .child(
-                                        TextView::new("✅ Address found!")
-                                            .style(cursive::theme::Effect::Bold),
-                                    )
-                                    .child(TextView::new(message))
-                                    .child(TextView::new(""))
-                                    .child(
-                                        TextView::new("📝 PUBLIC KEY:")
-                                            .style(cursive::theme::Effect::Bold),
-                                    )
-                                    .child(TextView::new(pub_key.clone()))
-                                    .child(TextView::new(""))
-                                    .child(
-                                        TextView::new("🔑 PRIVATE KEY:")
-                                            .style(cursive::theme::Effect::Bold),
-                                    )
-                                    .child(TextView::new(priv_key.clone()))
-                                    .child(TextView::new(""))
-                                    .child(
-                                        TextView::new(
-                                            "⚠️ IMPORTANT: Save your private key securely!",
-                                        )
-                                        .style(cursive::theme::Effect::Bold),
-                                    ),
-                            )
-                            .title("Vanity Address Generated")
-                            .button("Generate Another", |s| {
-                                s.pop_layer();
-                                main_form(s);
-                            })
-                            .button("Quit", |s| s.quit()),
-                        );
-                        s.set_autorefresh(false);
-                    }
-                } else if status == "error" {
-                    // Show error
-                    s.pop_layer(); // Remove progress dialog
-                    s.add_layer(
-                        Dialog::around(TextView::new(format!("❌ Error: {}", message)))
-                            .title("Error")
-                            .button("OK", |s| {
-                                s.pop_layer();
-                                main_form(s);
-                            }),
+    // Check for messages from the thread on every refresh
+    siv.clear_global_callbacks(Event::Refresh);
+    siv.set_on_pre_event(Event::Refresh, move |s| {
+        let Ok((status, message, percent, results)) = receiver.try_recv() else {
+            return;
+        };
+        // The progress dialog is gone if the user canceled the job
+        if s.find_name::<ProgressBar>("progress").is_none() {
+            s.clear_global_callbacks(Event::Refresh);
+            s.set_autorefresh(false);
+            return;
+        }
+
+        if status == "complete" {
+            // Found the matches! Show the results
+            let total = results.len();
+            let mut layout = LinearLayout::vertical()
+                .child(
+                    TextView::new(if total == 1 {
+                        "✅ Address found!"
+                    } else {
+                        "✅ Addresses found!"
+                    })
+                    .style(cursive::theme::Effect::Bold),
+                )
+                .child(TextView::new(message));
+            for (i, result) in results.into_iter().enumerate() {
+                layout.add_child(TextView::new(""));
+                if total > 1 {
+                    layout.add_child(
+                        TextView::new(format!("── Address {} of {} ──", i + 1, total))
+                            .style(cursive::theme::Effect::Bold),
+                    );
+                }
+                if let Some(pattern) = &result.matched_pattern {
+                    layout.add_child(TextView::new(format!("🎯 Matched pattern '{}'", pattern)));
+                }
+                layout
+                    .add_child(TextView::new("📝 PUBLIC KEY:").style(cursive::theme::Effect::Bold));
+                layout.add_child(TextView::new(result.public_key));
+                layout.add_child(
+                    TextView::new("🔑 PRIVATE KEY:").style(cursive::theme::Effect::Bold),
+                );
+                // Named so the dialog can drop them once it is dismissed
+                layout.add_child(TextView::new(result.private_key.expose()).with_name("secret"));
+                if let Some(mnemonic) = result.mnemonic {
+                    layout.add_child(
+                        TextView::new("🌱 SEED PHRASE:").style(cursive::theme::Effect::Bold),
                     );
-                    s.set_autorefresh(false);
-                } else {
-                    // Update the status
-                    s.call_on_name("status", |view: &mut TextView| {
-                        view.set_content(message);
-                    });
+                    layout.add_child(TextView::new(mnemonic.expose()).with_name("secret"));
                 }
             }
-        }))
-        .unwrap();
+            layout.add_child(TextView::new(""));
+            layout.add_child(
+                TextView::new("⚠️ IMPORTANT: Save your private key securely!")
+                    .style(cursive::theme::Effect::Bold),
+            );
+
+            s.pop_layer(); // Remove progress dialog
+            s.add_layer(
+                Dialog::around(layout.scrollable())
+                    .title("Vanity Address Generated")
+                    .button("Generate Another", |s| {
+                        clear_secrets(s);
+                        s.pop_layer();
+                        main_form(s);
+                    })
+                    .button("Quit", |s| {
+                        clear_secrets(s);
+                        s.quit();
+                    }),
+            );
+            s.clear_global_callbacks(Event::Refresh);
+            s.set_autorefresh(false);
+        } else if status == "error" {
+            // Show error
+            s.pop_layer(); // Remove progress dialog
+            s.add_layer(
+                Dialog::around(TextView::new(format!("❌ Error: {}", message)))
+                    .title("Error")
+                    .button("OK", |s| {
+                        s.pop_layer();
+                        main_form(s);
+                    }),
+            );
+            s.clear_global_callbacks(Event::Refresh);
+            s.set_autorefresh(false);
+        } else {
+            // Update the status and progress bar
+            s.call_on_name("status", |view: &mut TextView| {
+                view.set_content(message);
+            });
+            s.call_on_name("progress", |view: &mut ProgressBar| {
+                view.set_value(percent);
+            });
+        }
+    });
+}
+
+// Drop the shown keys rather than leave them in views for the rest of the
+// session. Cursive keeps its own copies of view content and has no way to
+// zeroize them, so this only frees that memory; it does not wipe it.
+fn clear_secrets(s: &mut Cursive) {
+    s.call_on_all_named("secret", |view: &mut TextView| view.set_content(""));
 }
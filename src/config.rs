@@ -0,0 +1,387 @@
+//! Settings for the API server.
+//!
+//! Each setting can come from several places. From lowest to highest
+//! precedence: built-in defaults, a TOML file, a `.env` file, environment
+//! variables and command-line flags. A `.env` file never overrides variables
+//! already set in the environment.
+
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Config file read when no other is named, if it exists.
+pub const DEFAULT_CONFIG_FILE: &str = "vanity.toml";
+
+/// The server's effective configuration.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    /// Search threads shared between the running jobs.
+    pub threads: usize,
+    /// Where the job store keeps its database and key.
+    pub data_dir: PathBuf,
+    pub max_running_jobs: usize,
+    pub max_queued_jobs: usize,
+    pub cleanup_interval_secs: u64,
+    pub ttl_complete_secs: u64,
+    pub ttl_retrieved_secs: u64,
+    pub ttl_error_secs: u64,
+    pub ttl_running_secs: u64,
+    /// Origins browsers may call the API from. Empty allows any origin.
+    pub cors_origins: Vec<String>,
+    /// Where the settings came from, lowest precedence first.
+    pub sources: Vec<String>,
+}
+
+/// Settings from one source. Unset fields fall through to the sources
+/// below it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub threads: Option<usize>,
+    pub data_dir: Option<PathBuf>,
+    pub max_running_jobs: Option<usize>,
+    pub max_queued_jobs: Option<usize>,
+    pub cleanup_interval_secs: Option<u64>,
+    pub ttl_complete_secs: Option<u64>,
+    pub ttl_retrieved_secs: Option<u64>,
+    pub ttl_error_secs: Option<u64>,
+    pub ttl_running_secs: Option<u64>,
+    pub cors_origins: Option<Vec<String>>,
+}
+
+impl ConfigLayer {
+    /// Reads a TOML file whose keys are the field names, e.g. `port = 3001`.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Reads `HOST`, `PORT` and the `VANITY_*` variables. CORS origins are
+    /// comma-separated.
+    pub fn from_env() -> Result<Self, String> {
+        Ok(Self {
+            host: env_var("HOST")?,
+            port: env_var("PORT")?,
+            threads: env_var("VANITY_THREADS")?,
+            data_dir: env_var("VANITY_DATA_DIR")?,
+            max_running_jobs: env_var("VANITY_MAX_RUNNING_JOBS")?,
+            max_queued_jobs: env_var("VANITY_MAX_QUEUED_JOBS")?,
+            cleanup_interval_secs: env_var("VANITY_CLEANUP_INTERVAL_SECS")?,
+            ttl_complete_secs: env_var("VANITY_TTL_COMPLETE_SECS")?,
+            ttl_retrieved_secs: env_var("VANITY_TTL_RETRIEVED_SECS")?,
+            ttl_error_secs: env_var("VANITY_TTL_ERROR_SECS")?,
+            ttl_running_secs: env_var("VANITY_TTL_RUNNING_SECS")?,
+            cors_origins: std::env::var("VANITY_CORS_ORIGINS")
+                .ok()
+                .map(|origins| split_origins(&origins)),
+        })
+    }
+
+    /// Whether any setting is present.
+    pub fn is_empty(&self) -> bool {
+        self.host.is_none()
+            && self.port.is_none()
+            && self.threads.is_none()
+            && self.data_dir.is_none()
+            && self.max_running_jobs.is_none()
+            && self.max_queued_jobs.is_none()
+            && self.cleanup_interval_secs.is_none()
+            && self.ttl_complete_secs.is_none()
+            && self.ttl_retrieved_secs.is_none()
+            && self.ttl_error_secs.is_none()
+            && self.ttl_running_secs.is_none()
+            && self.cors_origins.is_none()
+    }
+
+    /// `other` laid over this layer: its settings win where both have one.
+    pub fn merge(self, other: ConfigLayer) -> ConfigLayer {
+        ConfigLayer {
+            host: other.host.or(self.host),
+            port: other.port.or(self.port),
+            threads: other.threads.or(self.threads),
+            data_dir: other.data_dir.or(self.data_dir),
+            max_running_jobs: other.max_running_jobs.or(self.max_running_jobs),
+            max_queued_jobs: other.max_queued_jobs.or(self.max_queued_jobs),
+            cleanup_interval_secs: other.cleanup_interval_secs.or(self.cleanup_interval_secs),
+            ttl_complete_secs: other.ttl_complete_secs.or(self.ttl_complete_secs),
+            ttl_retrieved_secs: other.ttl_retrieved_secs.or(self.ttl_retrieved_secs),
+            ttl_error_secs: other.ttl_error_secs.or(self.ttl_error_secs),
+            ttl_running_secs: other.ttl_running_secs.or(self.ttl_running_secs),
+            cors_origins: other.cors_origins.or(self.cors_origins),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Layers the sources over the defaults. `config_file` names the TOML
+    /// file; without it `VANITY_CONFIG` does, and failing that
+    /// [`DEFAULT_CONFIG_FILE`] is read if present. `flags` holds the
+    /// command-line settings.
+    pub fn load(config_file: Option<&Path>, flags: ConfigLayer) -> Result<Self, String> {
+        let mut sources = vec!["defaults".to_string()];
+
+        // Only fills in variables the environment doesn't already set
+        let dotenv = match dotenvy::dotenv() {
+            Ok(path) => Some(path),
+            Err(e) if e.not_found() => None,
+            Err(e) => return Err(format!("Invalid .env file: {}", e)),
+        };
+
+        let named = config_file
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os("VANITY_CONFIG").map(PathBuf::from));
+        let file = match named {
+            Some(path) => Some(path),
+            None => Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|path| path.exists()),
+        };
+        let mut layer = ConfigLayer::default();
+        if let Some(path) = file {
+            layer = layer.merge(ConfigLayer::from_file(&path)?);
+            sources.push(path.display().to_string());
+        }
+
+        if let Some(path) = dotenv {
+            sources.push(path.display().to_string());
+        }
+        let env = ConfigLayer::from_env()?;
+        if !env.is_empty() {
+            sources.push("environment".to_string());
+        }
+        if !flags.is_empty() {
+            sources.push("command line".to_string());
+        }
+
+        let mut config = Self::from_layer(layer.merge(env).merge(flags))?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    /// The defaults with `layer` laid over them.
+    pub fn from_layer(layer: ConfigLayer) -> Result<Self, String> {
+        let config = ServerConfig {
+            host: layer.host.unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: layer.port.unwrap_or(3001),
+            threads: layer.threads.unwrap_or_else(num_cpus::get),
+            data_dir: layer.data_dir.unwrap_or_else(|| PathBuf::from("data")),
+            max_running_jobs: layer.max_running_jobs.unwrap_or(4),
+            max_queued_jobs: layer.max_queued_jobs.unwrap_or(100),
+            cleanup_interval_secs: layer.cleanup_interval_secs.unwrap_or(60),
+            ttl_complete_secs: layer.ttl_complete_secs.unwrap_or(24 * 3600),
+            ttl_retrieved_secs: layer.ttl_retrieved_secs.unwrap_or(10 * 60),
+            ttl_error_secs: layer.ttl_error_secs.unwrap_or(7 * 24 * 3600),
+            ttl_running_secs: layer.ttl_running_secs.unwrap_or(24 * 3600),
+            cors_origins: layer.cors_origins.unwrap_or_default(),
+            sources: Vec::new(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threads == 0 {
+            return Err("threads must be at least 1".to_string());
+        }
+        if self.max_running_jobs == 0 {
+            return Err("max_running_jobs must be at least 1".to_string());
+        }
+        if self.max_queued_jobs == 0 {
+            return Err("max_queued_jobs must be at least 1".to_string());
+        }
+        if self.cleanup_interval_secs == 0 {
+            return Err("cleanup_interval_secs must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// `host:port`, for binding and for the usage hints.
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl fmt::Display for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origins = if self.cors_origins.is_empty() {
+            "any".to_string()
+        } else {
+            self.cors_origins.join(", ")
+        };
+        writeln!(f, "  Sources:     {}", self.sources.join(" < "))?;
+        writeln!(f, "  Bind:        {}", self.bind_address())?;
+        writeln!(
+            f,
+            "  Jobs:        {} search threads shared by up to {} jobs, {} more queued",
+            self.threads, self.max_running_jobs, self.max_queued_jobs
+        )?;
+        writeln!(f, "  Data dir:    {}", self.data_dir.display())?;
+        writeln!(
+            f,
+            "  Retention:   complete {}s, retrieved {}s, error {}s, running {}s (checked every {}s)",
+            self.ttl_complete_secs,
+            self.ttl_retrieved_secs,
+            self.ttl_error_secs,
+            self.ttl_running_secs,
+            self.cleanup_interval_secs
+        )?;
+        write!(f, "  CORS:        {}", origins)
+    }
+}
+
+/// Comma-separated origins, as `VANITY_CORS_ORIGINS` and `--cors-origins`
+/// take them.
+pub fn split_origins(origins: &str) -> Vec<String> {
+    origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// An environment variable parsed as `T`, or None if it's unset
+fn env_var<T: FromStr>(name: &str) -> Result<Option<T>, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{} has an invalid value '{}'", name, value)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_layers_prefer_the_upper_one() {
+        let lower = ConfigLayer {
+            host: Some("0.0.0.0".to_string()),
+            port: Some(8080),
+            threads: Some(2),
+            ..ConfigLayer::default()
+        };
+        let upper = ConfigLayer {
+            port: Some(9090),
+            cors_origins: Some(vec!["https://example.com".to_string()]),
+            ..ConfigLayer::default()
+        };
+
+        let merged = lower.merge(upper);
+        assert_eq!(merged.host.as_deref(), Some("0.0.0.0"));
+        assert_eq!(merged.port, Some(9090));
+        assert_eq!(merged.threads, Some(2));
+        assert_eq!(
+            merged.cors_origins,
+            Some(vec!["https://example.com".to_string()])
+        );
+        assert_eq!(merged.max_queued_jobs, None);
+    }
+
+    #[test]
+    fn empty_layers_change_nothing() {
+        let layer = ConfigLayer {
+            port: Some(9090),
+            ..ConfigLayer::default()
+        };
+        assert!(ConfigLayer::default().is_empty());
+        assert!(!layer.is_empty());
+
+        let merged = layer.merge(ConfigLayer::default());
+        assert_eq!(merged.port, Some(9090));
+        assert_eq!(ConfigLayer::default().merge(merged).port, Some(9090));
+    }
+
+    #[test]
+    fn unset_settings_take_the_defaults() {
+        let config = ServerConfig::from_layer(ConfigLayer {
+            port: Some(9090),
+            ..ConfigLayer::default()
+        })
+        .unwrap();
+        assert_eq!(config.bind_address(), "127.0.0.1:9090");
+        assert_eq!(config.max_running_jobs, 4);
+        assert_eq!(config.max_queued_jobs, 100);
+        assert!(config.cors_origins.is_empty());
+    }
+
+    #[test]
+    fn zero_limits_are_rejected() {
+        let cases = [
+            (
+                ConfigLayer {
+                    threads: Some(0),
+                    ..ConfigLayer::default()
+                },
+                "threads must be at least 1",
+            ),
+            (
+                ConfigLayer {
+                    max_running_jobs: Some(0),
+                    ..ConfigLayer::default()
+                },
+                "max_running_jobs must be at least 1",
+            ),
+            (
+                ConfigLayer {
+                    max_queued_jobs: Some(0),
+                    ..ConfigLayer::default()
+                },
+                "max_queued_jobs must be at least 1",
+            ),
+            (
+                ConfigLayer {
+                    cleanup_interval_secs: Some(0),
+                    ..ConfigLayer::default()
+                },
+                "cleanup_interval_secs must be at least 1",
+            ),
+        ];
+        for (layer, error) in cases {
+            assert_eq!(ServerConfig::from_layer(layer).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn config_files_reject_unknown_keys() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vanity.toml");
+
+        std::fs::write(
+            &path,
+            "port = 3002\ncors_origins = [\"https://a.example\"]\n",
+        )
+        .unwrap();
+        let layer = ConfigLayer::from_file(&path).unwrap();
+        assert_eq!(layer.port, Some(3002));
+        assert_eq!(
+            layer.cors_origins,
+            Some(vec!["https://a.example".to_string()])
+        );
+
+        std::fs::write(&path, "prot = 3002\n").unwrap();
+        let err = ConfigLayer::from_file(&path).unwrap_err();
+        assert!(err.starts_with("Invalid config file"), "{}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn origins_are_split_on_commas_and_trimmed() {
+        assert_eq!(
+            split_origins(" https://a.example , https://b.example,,"),
+            ["https://a.example", "https://b.example"]
+        );
+        assert!(split_origins("").is_empty());
+        assert!(split_origins(" , ").is_empty());
+    }
+}
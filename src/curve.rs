@@ -0,0 +1,540 @@
+//! Ed25519 public keys computed in batches.
+//!
+//! `curve25519-dalek` multiplies the base point into projective
+//! coordinates, then `compress()` spends a field inversion per point to
+//! bring them back to affine. That inversion is a large share of each key,
+//! and dalek keeps the coordinates private, so they can't be normalized
+//! together. This module does the same constant-time fixed-base
+//! multiplication itself and normalizes a whole batch with one inversion
+//! (Montgomery's trick). Every key still comes from its own standard seed;
+//! only the final division is shared.
+
+use sha2::{Digest, Sha512};
+use std::sync::OnceLock;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+// Low 51 bits of a limb
+const MASK: u64 = (1 << 51) - 1;
+
+// Affine coordinates of the ed25519 base point, little-endian
+const BASEPOINT_X: [u8; 32] = [
+    0x1a, 0xd5, 0x25, 0x8f, 0x60, 0x2d, 0x56, 0xc9, 0xb2, 0xa7, 0x25, 0x95, 0x60, 0xc7, 0x2c, 0x69,
+    0x5c, 0xdc, 0xd6, 0xfd, 0x31, 0xe2, 0xa4, 0xc0, 0xfe, 0x53, 0x6e, 0xcd, 0xd3, 0x36, 0x69, 0x21,
+];
+const BASEPOINT_Y: [u8; 32] = [
+    0x58, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+    0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+];
+
+/// Public key bytes for each of `seeds`, the keys `Keypair::from_bytes`
+/// pairs with them, for one shared field inversion.
+pub fn public_keys<const N: usize>(seeds: &[[u8; 32]; N]) -> [[u8; 32]; N] {
+    let table = basepoint_table();
+    let points: Vec<ExtendedPoint> = seeds
+        .iter()
+        .map(|seed| {
+            let mut scalar = clamped_scalar(seed);
+            let point = table.mul(&scalar);
+            scalar.zeroize();
+            point
+        })
+        .collect();
+
+    let mut inverses: Vec<FieldElement> = points.iter().map(|point| point.z).collect();
+    FieldElement::batch_invert(&mut inverses);
+
+    let mut keys = [[0u8; 32]; N];
+    for ((key, point), z_inverse) in keys.iter_mut().zip(&points).zip(&inverses) {
+        *key = compress(&point.x.mul(z_inverse), &point.y.mul(z_inverse));
+    }
+    keys
+}
+
+// The clamped scalar RFC 8032 derives from a seed
+fn clamped_scalar(seed: &[u8; 32]) -> [u8; 32] {
+    let mut hash = Sha512::digest(seed);
+    let mut bits = [0u8; 32];
+    bits.copy_from_slice(&hash[..32]);
+    hash.zeroize();
+
+    bits[0] &= 248;
+    bits[31] &= 127;
+    bits[31] |= 64;
+    bits
+}
+
+// The encoding of the affine point (x, y): y with the sign of x on top
+fn compress(x: &FieldElement, y: &FieldElement) -> [u8; 32] {
+    let mut bytes = y.to_bytes();
+    bytes[31] ^= x.is_negative() << 7;
+    bytes
+}
+
+// An element of GF(2^255 - 19) as five 51-bit limbs. Limbs may run a few
+// bits over 51 between reductions.
+#[derive(Debug, Clone, Copy, Default)]
+struct FieldElement([u64; 5]);
+
+impl FieldElement {
+    const ZERO: Self = Self([0; 5]);
+    const ONE: Self = Self([1, 0, 0, 0, 0]);
+
+    fn from_u64(value: u64) -> Self {
+        Self([value & MASK, value >> 51, 0, 0, 0])
+    }
+
+    // Ignores the top bit, as the encoding does
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let word = |i: usize| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+        let (w0, w1, w2, w3) = (word(0), word(1), word(2), word(3));
+        Self([
+            w0 & MASK,
+            ((w0 >> 51) | (w1 << 13)) & MASK,
+            ((w1 >> 38) | (w2 << 26)) & MASK,
+            ((w2 >> 25) | (w3 << 39)) & MASK,
+            (w3 >> 12) & MASK,
+        ])
+    }
+
+    // Canonical little-endian encoding, fully reduced mod p
+    fn to_bytes(self) -> [u8; 32] {
+        let mut limbs = Self::reduce(self.0).0;
+
+        // Adding 19 carries out of the top limb exactly when the value is at
+        // least p, in which case subtract p by adding 19 and dropping 2^255
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= MASK;
+        }
+        limbs[4] &= MASK;
+
+        let mut bytes = [0u8; 32];
+        let (mut acc, mut bits, mut out) = (0u128, 0, 0);
+        for limb in limbs {
+            acc |= (limb as u128) << bits;
+            bits += 51;
+            while bits >= 8 && out < 32 {
+                bytes[out] = acc as u8;
+                acc >>= 8;
+                bits -= 8;
+                out += 1;
+            }
+        }
+        bytes[31] = acc as u8;
+        bytes
+    }
+
+    fn is_negative(&self) -> u8 {
+        self.to_bytes()[0] & 1
+    }
+
+    // Carries each limb's excess into the next, folding the top one back
+    // in as 19 (since 2^255 = 19 mod p)
+    fn reduce(mut limbs: [u64; 5]) -> Self {
+        let carries = limbs.map(|limb| limb >> 51);
+        for limb in &mut limbs {
+            *limb &= MASK;
+        }
+        limbs[0] += carries[4] * 19;
+        for i in 1..5 {
+            limbs[i] += carries[i - 1];
+        }
+        Self(limbs)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut limbs = self.0;
+        for (limb, other) in limbs.iter_mut().zip(other.0) {
+            *limb += other;
+        }
+        Self::reduce(limbs)
+    }
+
+    // Adds 16p first so no limb underflows
+    fn sub(&self, other: &Self) -> Self {
+        const SIXTEEN_P: [u64; 5] = [
+            36028797018963664,
+            36028797018963952,
+            36028797018963952,
+            36028797018963952,
+            36028797018963952,
+        ];
+        let mut limbs = [0u64; 5];
+        for i in 0..5 {
+            limbs[i] = self.0[i] + SIXTEEN_P[i] - other.0[i];
+        }
+        Self::reduce(limbs)
+    }
+
+    fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let m = |a: u64, b: u64| (a as u128) * (b as u128);
+        let [a0, a1, a2, a3, a4] = self.0;
+        let [b0, b1, b2, b3, b4] = other.0;
+        let (b1_19, b2_19, b3_19, b4_19) = (b1 * 19, b2 * 19, b3 * 19, b4 * 19);
+
+        let c0 = m(a0, b0) + m(a4, b1_19) + m(a3, b2_19) + m(a2, b3_19) + m(a1, b4_19);
+        let c1 = m(a1, b0) + m(a0, b1) + m(a4, b2_19) + m(a3, b3_19) + m(a2, b4_19);
+        let c2 = m(a2, b0) + m(a1, b1) + m(a0, b2) + m(a4, b3_19) + m(a3, b4_19);
+        let c3 = m(a3, b0) + m(a2, b1) + m(a1, b2) + m(a0, b3) + m(a4, b4_19);
+        let c4 = m(a4, b0) + m(a3, b1) + m(a2, b2) + m(a1, b3) + m(a0, b4);
+        Self::carry_wide([c0, c1, c2, c3, c4])
+    }
+
+    fn square(&self) -> Self {
+        let m = |a: u64, b: u64| (a as u128) * (b as u128);
+        let [a0, a1, a2, a3, a4] = self.0;
+        let (a0_2, a1_2) = (a0 * 2, a1 * 2);
+        let (a1_38, a2_38, a3_38) = (a1 * 38, a2 * 38, a3 * 38);
+        let (a3_19, a4_19) = (a3 * 19, a4 * 19);
+
+        let c0 = m(a0, a0) + m(a1_38, a4) + m(a2_38, a3);
+        let c1 = m(a0_2, a1) + m(a2_38, a4) + m(a3_19, a3);
+        let c2 = m(a0_2, a2) + m(a1, a1) + m(a3_38, a4);
+        let c3 = m(a0_2, a3) + m(a1_2, a2) + m(a4_19, a4);
+        let c4 = m(a0_2, a4) + m(a1_2, a3) + m(a2, a2);
+        Self::carry_wide([c0, c1, c2, c3, c4])
+    }
+
+    // Squares `k` times in a row
+    fn pow2k(&self, k: u32) -> Self {
+        (0..k).fold(*self, |value, _| value.square())
+    }
+
+    // Reduces 128-bit column sums back to 51-bit limbs
+    fn carry_wide(mut c: [u128; 5]) -> Self {
+        let mut limbs = [0u64; 5];
+        for i in 0..4 {
+            c[i + 1] += c[i] >> 51;
+            limbs[i] = (c[i] as u64) & MASK;
+        }
+        limbs[4] = (c[4] as u64) & MASK;
+        limbs[0] += (c[4] >> 51) as u64 * 19;
+        limbs[1] += limbs[0] >> 51;
+        limbs[0] &= MASK;
+        Self(limbs)
+    }
+
+    // self^(p - 2) = 1/self, by the usual addition chain
+    fn invert(&self) -> Self {
+        let t0 = self.square(); // 2
+        let t1 = t0.pow2k(2); // 8
+        let t2 = self.mul(&t1); // 9
+        let t3 = t0.mul(&t2); // 11
+        let t4 = t3.square(); // 22
+        let t5 = t2.mul(&t4); // 2^5 - 1
+        let t7 = t5.pow2k(5).mul(&t5); // 2^10 - 1
+        let t9 = t7.pow2k(10).mul(&t7); // 2^20 - 1
+        let t11 = t9.pow2k(20).mul(&t9); // 2^40 - 1
+        let t13 = t11.pow2k(10).mul(&t7); // 2^50 - 1
+        let t15 = t13.pow2k(50).mul(&t13); // 2^100 - 1
+        let t17 = t15.pow2k(100).mul(&t15); // 2^200 - 1
+        let t19 = t17.pow2k(50).mul(&t13); // 2^250 - 1
+        t19.pow2k(5).mul(&t3) // 2^255 - 21
+    }
+
+    // Replaces every element by its inverse, for one inversion in all.
+    // None of them may be zero.
+    fn batch_invert(elements: &mut [Self]) {
+        let mut products = Vec::with_capacity(elements.len());
+        let mut product = Self::ONE;
+        for element in elements.iter() {
+            products.push(product);
+            product = product.mul(element);
+        }
+
+        let mut inverse = product.invert();
+        for (element, before) in elements.iter_mut().zip(products).rev() {
+            let next = inverse.mul(element);
+            *element = inverse.mul(&before);
+            inverse = next;
+        }
+    }
+}
+
+impl ConditionallySelectable for FieldElement {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut limbs = [0u64; 5];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::conditional_select(&a.0[i], &b.0[i], choice);
+        }
+        Self(limbs)
+    }
+}
+
+// A point in extended twisted Edwards coordinates: x = X/Z, y = Y/Z and
+// xy = T/Z
+#[derive(Debug, Clone, Copy)]
+struct ExtendedPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+// An intermediate result of addition and doubling, before it is mapped back
+// to extended or projective coordinates
+struct CompletedPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+// An affine point prepared for mixed addition: (y + x, y - x, 2dxy)
+#[derive(Debug, Clone, Copy)]
+struct AffineNielsPoint {
+    y_plus_x: FieldElement,
+    y_minus_x: FieldElement,
+    xy2d: FieldElement,
+}
+
+impl ExtendedPoint {
+    fn identity() -> Self {
+        Self {
+            x: FieldElement::ZERO,
+            y: FieldElement::ONE,
+            z: FieldElement::ONE,
+            t: FieldElement::ZERO,
+        }
+    }
+
+    fn from_affine(x: FieldElement, y: FieldElement) -> Self {
+        Self {
+            x,
+            y,
+            z: FieldElement::ONE,
+            t: x.mul(&y),
+        }
+    }
+
+    fn add(&self, other: &AffineNielsPoint) -> Self {
+        let pp = self.y.add(&self.x).mul(&other.y_plus_x);
+        let mm = self.y.sub(&self.x).mul(&other.y_minus_x);
+        let txy2d = self.t.mul(&other.xy2d);
+        let z2 = self.z.add(&self.z);
+        CompletedPoint {
+            x: pp.sub(&mm),
+            y: pp.add(&mm),
+            z: z2.add(&txy2d),
+            t: z2.sub(&txy2d),
+        }
+        .to_extended()
+    }
+
+    fn double(&self) -> CompletedPoint {
+        let xx = self.x.square();
+        let yy = self.y.square();
+        let zz = self.z.square();
+        let zz2 = zz.add(&zz);
+        let x_plus_y_squared = self.x.add(&self.y).square();
+        let yy_plus_xx = yy.add(&xx);
+        let yy_minus_xx = yy.sub(&xx);
+        CompletedPoint {
+            x: x_plus_y_squared.sub(&yy_plus_xx),
+            y: yy_plus_xx,
+            z: yy_minus_xx,
+            t: zz2.sub(&yy_minus_xx),
+        }
+    }
+
+    // 16 times this point. The intermediate doublings skip T, which
+    // doubling doesn't read.
+    fn mul_by_16(&self) -> Self {
+        let mut point = *self;
+        for _ in 0..3 {
+            let doubled = point.double();
+            point = Self {
+                x: doubled.x.mul(&doubled.t),
+                y: doubled.y.mul(&doubled.z),
+                z: doubled.z.mul(&doubled.t),
+                t: FieldElement::ZERO,
+            };
+        }
+        point.double().to_extended()
+    }
+}
+
+impl CompletedPoint {
+    fn to_extended(&self) -> ExtendedPoint {
+        ExtendedPoint {
+            x: self.x.mul(&self.t),
+            y: self.y.mul(&self.z),
+            z: self.z.mul(&self.t),
+            t: self.x.mul(&self.y),
+        }
+    }
+}
+
+impl AffineNielsPoint {
+    fn identity() -> Self {
+        Self {
+            y_plus_x: FieldElement::ONE,
+            y_minus_x: FieldElement::ONE,
+            xy2d: FieldElement::ZERO,
+        }
+    }
+
+    fn conditional_assign(&mut self, other: &Self, choice: Choice) {
+        self.y_plus_x.conditional_assign(&other.y_plus_x, choice);
+        self.y_minus_x.conditional_assign(&other.y_minus_x, choice);
+        self.xy2d.conditional_assign(&other.xy2d, choice);
+    }
+}
+
+// Multiples 1B to 8B of B = 256^i times the base point, for i from 0 to 31,
+// so a scalar in signed radix-16 digits takes one lookup per digit
+struct BasepointTable([[AffineNielsPoint; 8]; 32]);
+
+fn basepoint_table() -> &'static BasepointTable {
+    static TABLE: OnceLock<BasepointTable> = OnceLock::new();
+    TABLE.get_or_init(BasepointTable::new)
+}
+
+impl BasepointTable {
+    fn new() -> Self {
+        // d = -121665/121666
+        let d = FieldElement::from_u64(121665)
+            .neg()
+            .mul(&FieldElement::from_u64(121666).invert());
+        let d2 = d.add(&d);
+        let niels = |x: FieldElement, y: FieldElement| AffineNielsPoint {
+            y_plus_x: y.add(&x),
+            y_minus_x: y.sub(&x),
+            xy2d: x.mul(&y).mul(&d2),
+        };
+
+        let mut x = FieldElement::from_bytes(&BASEPOINT_X);
+        let mut y = FieldElement::from_bytes(&BASEPOINT_Y);
+        let mut rows = [[AffineNielsPoint::identity(); 8]; 32];
+        for row in &mut rows {
+            let base = niels(x, y);
+            let mut multiples = [ExtendedPoint::from_affine(x, y); 8];
+            for j in 1..8 {
+                multiples[j] = multiples[j - 1].add(&base);
+            }
+            let mut inverses = multiples.map(|point| point.z);
+            FieldElement::batch_invert(&mut inverses);
+            for ((entry, point), z_inverse) in row.iter_mut().zip(&multiples).zip(&inverses) {
+                *entry = niels(point.x.mul(z_inverse), point.y.mul(z_inverse));
+            }
+
+            // On to 256 times the base of this row
+            let next = ExtendedPoint::from_affine(x, y).mul_by_16().mul_by_16();
+            let z_inverse = next.z.invert();
+            x = next.x.mul(&z_inverse);
+            y = next.y.mul(&z_inverse);
+        }
+        Self(rows)
+    }
+
+    // The entry for `digit` in -8..=8, looked up without branching on it
+    fn select(&self, row: usize, digit: i8) -> AffineNielsPoint {
+        let sign = (digit as i16) >> 7;
+        let magnitude = (((digit as i16) + sign) ^ sign) as u8;
+
+        let mut entry = AffineNielsPoint::identity();
+        for (j, candidate) in self.0[row].iter().enumerate() {
+            entry.conditional_assign(candidate, magnitude.ct_eq(&(j as u8 + 1)));
+        }
+        let negated = AffineNielsPoint {
+            y_plus_x: entry.y_minus_x,
+            y_minus_x: entry.y_plus_x,
+            xy2d: entry.xy2d.neg(),
+        };
+        entry.conditional_assign(&negated, Choice::from((sign & 1) as u8));
+        entry
+    }
+
+    // `scalar` times the base point, for a scalar below 2^255. Sums the odd
+    // digits, multiplies by 16, then adds the even ones, as dalek does.
+    fn mul(&self, scalar: &[u8; 32]) -> ExtendedPoint {
+        let mut digits = radix_16(scalar);
+        let mut point = ExtendedPoint::identity();
+        for i in (1..64).step_by(2) {
+            point = point.add(&self.select(i / 2, digits[i]));
+        }
+        point = point.mul_by_16();
+        for i in (0..64).step_by(2) {
+            point = point.add(&self.select(i / 2, digits[i]));
+        }
+        digits.zeroize();
+        point
+    }
+}
+
+// Signed radix-16 digits in -8..=8, least significant first
+fn radix_16(scalar: &[u8; 32]) -> [i8; 64] {
+    let mut digits = [0i8; 64];
+    for (i, byte) in scalar.iter().enumerate() {
+        digits[2 * i] = (byte & 15) as i8;
+        digits[2 * i + 1] = (byte >> 4) as i8;
+    }
+    for i in 0..63 {
+        let carry = (digits[i] + 8) >> 4;
+        digits[i] -= carry << 4;
+        digits[i + 1] += carry;
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keygen::public_key;
+    use curve25519_dalek::constants::ED25519_BASEPOINT_COMPRESSED;
+    use rand_core::{OsRng, RngCore};
+
+    #[test]
+    fn basepoint_encodes_like_dalek() {
+        let x = FieldElement::from_bytes(&BASEPOINT_X);
+        let y = FieldElement::from_bytes(&BASEPOINT_Y);
+        assert_eq!(compress(&x, &y), ED25519_BASEPOINT_COMPRESSED.to_bytes());
+    }
+
+    #[test]
+    fn inversion_round_trips() {
+        let mut bytes = [0u8; 32];
+        for _ in 0..100 {
+            OsRng.fill_bytes(&mut bytes);
+            let element = FieldElement::from_bytes(&bytes);
+            let product = element.mul(&element.invert());
+            assert_eq!(product.to_bytes(), FieldElement::ONE.to_bytes());
+        }
+    }
+
+    #[test]
+    fn batched_keys_match_dalek() {
+        let mut seeds = [[0u8; 32]; 64];
+        for _ in 0..20 {
+            for seed in &mut seeds {
+                OsRng.fill_bytes(seed);
+            }
+            let keys = public_keys(&seeds);
+            for (seed, key) in seeds.iter().zip(&keys) {
+                assert_eq!(*key, public_key(seed));
+            }
+        }
+    }
+
+    #[test]
+    fn encoding_is_canonical() {
+        // p + 3 reduces to 3
+        let mut p_plus_3 = [0xffu8; 32];
+        p_plus_3[0] = 0xf0;
+        p_plus_3[31] = 0x7f;
+        assert_eq!(
+            FieldElement::from_bytes(&p_plus_3).to_bytes(),
+            FieldElement::from_u64(3).to_bytes()
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Vanity accounts derived with `Pubkey::create_with_seed`.
+//!
+//! Stake and nonce accounts can live at `create_with_seed(base, seed, owner)`
+//! instead of a fresh keypair's address. The base key signs for them, so a
+//! search only needs to vary the seed string.
+
+use crate::matcher::BASE58_ALPHABET;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::{Pubkey, MAX_SEED_LEN};
+use std::str::FromStr;
+
+// Base58 digits in the largest u64 counter
+const MAX_COUNTER_LEN: usize = 11;
+
+/// Longest seed prefix that still leaves room for any counter.
+pub const MAX_SEED_PREFIX_LEN: usize = MAX_SEED_LEN - MAX_COUNTER_LEN;
+
+/// Search for a seed that derives a matching account from a base key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedAccountOptions {
+    /// Base58 key that signs for the derived account.
+    pub base: String,
+    /// Base58 ID of the program that will own the account, e.g. the stake
+    /// program.
+    pub owner: String,
+    /// Fixed start of the seed; a base58 counter follows it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub seed_prefix: String,
+    /// Where the counter starts, e.g. to carry on past the matches of an
+    /// earlier search.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub start_counter: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+impl DerivedAccountOptions {
+    pub fn new(base: impl Into<String>, owner: impl Into<String>) -> Self {
+        Self {
+            base: base.into(),
+            owner: owner.into(),
+            seed_prefix: String::new(),
+            start_counter: 0,
+        }
+    }
+
+    /// Starts every seed with `seed_prefix`.
+    pub fn with_seed_prefix(mut self, seed_prefix: impl Into<String>) -> Self {
+        self.seed_prefix = seed_prefix.into();
+        self
+    }
+
+    /// Starts the counter at `start_counter` instead of zero.
+    pub fn with_start_counter(mut self, start_counter: u64) -> Self {
+        self.start_counter = start_counter;
+        self
+    }
+
+    /// The counter behind `seed`, if this search could have produced it.
+    pub fn counter(&self, seed: &str) -> Option<u64> {
+        let digits = seed
+            .strip_prefix(self.seed_prefix.as_str())
+            .filter(|digits| !digits.is_empty())?;
+        let bytes = bs58::decode(digits).into_vec().ok()?;
+        let start = bytes.len().saturating_sub(8);
+        if bytes[..start].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        let mut counter = [0u8; 8];
+        counter[8 - (bytes.len() - start)..].copy_from_slice(&bytes[start..]);
+        Some(u64::from_be_bytes(counter))
+    }
+
+    pub fn base(&self) -> Result<Pubkey, String> {
+        Pubkey::from_str(&self.base).map_err(|_| format!("Invalid base key '{}'", self.base))
+    }
+
+    pub fn owner(&self) -> Result<Pubkey, String> {
+        Pubkey::from_str(&self.owner).map_err(|_| format!("Invalid owner '{}'", self.owner))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let base = self.base()?;
+        let owner = self.owner()?;
+        if self.seed_prefix.len() > MAX_SEED_PREFIX_LEN {
+            return Err(format!(
+                "Seed prefix must be at most {} bytes so the counter fits in {}",
+                MAX_SEED_PREFIX_LEN, MAX_SEED_LEN
+            ));
+        }
+        // Rejects owners that would make the address look like a PDA
+        Pubkey::create_with_seed(&base, &self.seed_prefix, &owner)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Per-thread source of candidate derived accounts.
+pub struct DerivedAccountStream {
+    base: Pubkey,
+    owner: Pubkey,
+    prefix: String,
+    seed: String,
+    // Workers interleave counters so none is tried twice
+    counter: u64,
+    step: u64,
+}
+
+impl DerivedAccountStream {
+    /// Starts worker `worker` of `workers` on the search `options` describes.
+    /// The options must be valid.
+    pub fn new(options: &DerivedAccountOptions, worker: usize, workers: usize) -> Self {
+        Self {
+            base: options
+                .base()
+                .expect("derived account options are validated"),
+            owner: options
+                .owner()
+                .expect("derived account options are validated"),
+            prefix: options.seed_prefix.clone(),
+            seed: String::with_capacity(MAX_SEED_LEN),
+            counter: options.start_counter.wrapping_add(worker as u64),
+            step: workers as u64,
+        }
+    }
+
+    /// Returns a fresh candidate's address bytes and the counter behind its
+    /// seed.
+    pub fn next_candidate(&mut self) -> ([u8; 32], u64) {
+        let counter = self.counter;
+        self.counter = self.counter.wrapping_add(self.step);
+
+        // Reuse one buffer rather than allocating a seed per attempt
+        self.seed.clear();
+        self.seed.push_str(&self.prefix);
+        push_counter(&mut self.seed, counter);
+        // What `Pubkey::create_with_seed` hashes; base58 keeps every counter
+        // within the room the prefix leaves, and `validate` rules out owners
+        // it rejects
+        let address = Sha256::new()
+            .chain(self.base)
+            .chain(&self.seed)
+            .chain(self.owner)
+            .finalize();
+        (address.into(), counter)
+    }
+
+    /// The seed string of the candidate numbered `counter`.
+    pub fn seed(&self, counter: u64) -> String {
+        let mut seed = self.prefix.clone();
+        push_counter(&mut seed, counter);
+        seed
+    }
+}
+
+// Appends `counter` in base58, most significant digit first
+fn push_counter(seed: &mut String, mut counter: u64) {
+    let alphabet = BASE58_ALPHABET.as_bytes();
+    let mut digits = [0u8; MAX_COUNTER_LEN];
+    let mut start = MAX_COUNTER_LEN;
+    loop {
+        start -= 1;
+        digits[start] = alphabet[(counter % 58) as usize];
+        counter /= 58;
+        if counter == 0 {
+            break;
+        }
+    }
+    seed.extend(digits[start..].iter().map(|&digit| digit as char));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> DerivedAccountOptions {
+        let stake = "Stake11111111111111111111111111111111111111";
+        DerivedAccountOptions::new(Pubkey::new_unique().to_string(), stake)
+            .with_seed_prefix("p".repeat(MAX_SEED_PREFIX_LEN))
+    }
+
+    #[test]
+    fn candidates_match_create_with_seed() {
+        let options = options();
+        let (base, owner) = (options.base().unwrap(), options.owner().unwrap());
+        let mut accounts = DerivedAccountStream::new(&options, 0, 1);
+        for _ in 0..100 {
+            let (address, counter) = accounts.next_candidate();
+            let seed = accounts.seed(counter);
+            assert_eq!(
+                address,
+                Pubkey::create_with_seed(&base, &seed, &owner)
+                    .unwrap()
+                    .to_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn largest_counter_fits_after_longest_prefix() {
+        let options = options();
+        let mut accounts = DerivedAccountStream::new(&options, 0, 1);
+        accounts.counter = u64::MAX;
+        let (address, counter) = accounts.next_candidate();
+        let seed = accounts.seed(counter);
+        assert_eq!(seed.len(), MAX_SEED_LEN);
+        assert_eq!(
+            address,
+            Pubkey::create_with_seed(&options.base().unwrap(), &seed, &options.owner().unwrap())
+                .unwrap()
+                .to_bytes()
+        );
+    }
+
+    #[test]
+    fn counters_are_base58() {
+        let accounts = DerivedAccountStream::new(&options().with_seed_prefix("s-"), 0, 1);
+        assert_eq!(accounts.seed(0), "s-1");
+        assert_eq!(accounts.seed(57), "s-z");
+        assert_eq!(accounts.seed(58), "s-21");
+        let options = options().with_seed_prefix("s-");
+        for counter in [0, 57, 58, 1 << 40, u64::MAX] {
+            assert_eq!(options.counter(&accounts.seed(counter)), Some(counter));
+        }
+        assert_eq!(options.counter("s-"), None);
+        assert_eq!(options.counter("t-21"), None);
+        assert_eq!(
+            accounts.seed(u64::MAX),
+            format!("s-{}", bs58::encode(u64::MAX.to_be_bytes()).into_string())
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Results sealed to a client's age key.
+//!
+//! A server that hands out raw private keys gives them to anyone who learns
+//! the job ID. Sealing a result to an X25519 age recipient (`age1...`) leaves
+//! only the public parts readable; the secrets travel as an ASCII-armored age
+//! file that the matching identity (`AGE-SECRET-KEY-1...`) opens, whether in
+//! the `vanity` CLI or with `age --decrypt`.
+
+use crate::generator::{KeyFormat, VanityResult};
+use crate::secret::SecretString;
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::x25519::{Identity, Recipient};
+use age::{Decryptor, Encryptor, IdentityFile};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// The secret fields of a result, as the sealed payload holds them.
+#[derive(Serialize, Deserialize)]
+struct Secrets {
+    #[serde(default, skip_serializing_if = "SecretString::is_empty")]
+    private_key: SecretString,
+    #[serde(default)]
+    format: KeyFormat,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mnemonic: Option<SecretString>,
+}
+
+/// Parses an `age1...` recipient.
+pub fn parse_recipient(recipient: &str) -> Result<Recipient, String> {
+    Recipient::from_str(recipient.trim())
+        .map_err(|_| format!("Invalid age recipient '{}'", recipient))
+}
+
+/// Reads the X25519 identities of an age identity file, as `age-keygen`
+/// writes it.
+pub fn parse_identities(contents: &str) -> Result<Vec<Identity>, String> {
+    let identities = IdentityFile::from_buffer(contents.as_bytes())
+        .map_err(|e| format!("Invalid age identity file: {}", e))?
+        .into_identities();
+    if identities.is_empty() {
+        return Err("The age identity file holds no X25519 identities".to_string());
+    }
+    Ok(identities)
+}
+
+/// Encrypts `plaintext` to `recipient` as an ASCII-armored age file.
+pub fn seal(recipient: &Recipient, plaintext: &[u8]) -> Result<String, String> {
+    let encryptor = Encryptor::with_recipients(vec![Box::new(recipient.clone())]);
+    let mut sealed = Vec::new();
+    let armor =
+        ArmoredWriter::wrap_output(&mut sealed, Format::AsciiArmor).map_err(|e| e.to_string())?;
+    let mut writer = encryptor.wrap_output(armor).map_err(|e| e.to_string())?;
+    writer.write_all(plaintext).map_err(|e| e.to_string())?;
+    writer
+        .finish()
+        .and_then(|armor| armor.finish())
+        .map_err(|e| e.to_string())?;
+    Ok(String::from_utf8(sealed).expect("armor is ASCII"))
+}
+
+/// Decrypts an armored age file written by [`seal`] with any of
+/// `identities`.
+pub fn open(identities: &[Identity], sealed: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    let decryptor = match Decryptor::new(ArmoredReader::new(sealed.as_bytes())) {
+        Ok(Decryptor::Recipients(decryptor)) => decryptor,
+        Ok(_) => return Err("Sealed with a passphrase, not a key".to_string()),
+        Err(e) => return Err(format!("Invalid age file: {}", e)),
+    };
+    let mut plaintext = Zeroizing::new(Vec::new());
+    decryptor
+        .decrypt(
+            identities
+                .iter()
+                .map(|identity| identity as &dyn age::Identity),
+        )
+        .map_err(|e| format!("Couldn't decrypt: {}", e))?
+        .read_to_end(&mut plaintext)
+        .map_err(|e| format!("Couldn't decrypt: {}", e))?;
+    Ok(plaintext)
+}
+
+impl VanityResult {
+    /// Moves the private key and seed phrase into an age file only
+    /// `recipient` can open, leaving the public fields readable.
+    pub fn encrypt_to(mut self, recipient: &Recipient) -> Result<Self, String> {
+        let secrets = Secrets {
+            private_key: std::mem::take(&mut self.private_key),
+            format: self.format,
+            mnemonic: self.mnemonic.take(),
+        };
+        self.format = KeyFormat::Base58;
+        let plaintext = Zeroizing::new(serde_json::to_vec(&secrets).unwrap());
+
+        self.encrypted = Some(seal(recipient, &plaintext)?);
+        Ok(self)
+    }
+
+    /// Restores the secrets of a result sealed by
+    /// [`VanityResult::encrypt_to`]. Results that aren't sealed pass through.
+    pub fn decrypt_with(mut self, identities: &[Identity]) -> Result<Self, String> {
+        let Some(sealed) = self.encrypted.take() else {
+            return Ok(self);
+        };
+        let plaintext = open(identities, &sealed)?;
+        let secrets: Secrets = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Invalid encrypted result: {}", e))?;
+        self.private_key = secrets.private_key;
+        self.format = secrets.format;
+        self.mnemonic = secrets.mnemonic;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    const PHRASE: &str =
+        "pill tomorrow foster begin walnut borrow virtual kick shift mutual shoe scatter";
+
+    fn result() -> (VanityResult, String) {
+        let keypair = Keypair::new();
+        let private_key = bs58::encode(keypair.to_bytes()).into_string();
+        let result: VanityResult = serde_json::from_value(serde_json::json!({
+            "public_key": keypair.pubkey().to_string(),
+            "private_key": private_key,
+            "mnemonic": PHRASE,
+        }))
+        .unwrap();
+        (result, private_key)
+    }
+
+    #[test]
+    fn sealed_bytes_open_with_the_identity() {
+        let identity = Identity::generate();
+        let sealed = seal(&identity.to_public(), b"secret").unwrap();
+        assert!(sealed.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+        assert!(!sealed.contains("secret"));
+        assert_eq!(open(&[identity], &sealed).unwrap().as_slice(), b"secret");
+    }
+
+    #[test]
+    fn other_identities_cant_open_sealed_bytes() {
+        let sealed = seal(&Identity::generate().to_public(), b"secret").unwrap();
+        let err = open(&[Identity::generate()], &sealed).unwrap_err();
+        assert!(err.starts_with("Couldn't decrypt"), "{}", err);
+        assert!(open(&[Identity::generate()], "not age").is_err());
+    }
+
+    #[test]
+    fn encrypted_results_keep_only_public_fields_readable() {
+        let identity = Identity::generate();
+        let (result, private_key) = result();
+        let public_key = result.public_key.clone();
+
+        let sealed = result.encrypt_to(&identity.to_public()).unwrap();
+        assert!(sealed.private_key.is_empty());
+        assert!(sealed.mnemonic.is_none());
+        assert_eq!(sealed.public_key, public_key);
+        let json = serde_json::to_string(&sealed).unwrap();
+        assert!(!json.contains(&private_key));
+        assert!(!json.contains("walnut"));
+
+        let opened = sealed.decrypt_with(&[identity]).unwrap();
+        assert_eq!(opened.private_key.expose(), private_key);
+        assert_eq!(opened.mnemonic.unwrap().expose(), PHRASE);
+        assert!(opened.encrypted.is_none());
+    }
+
+    #[test]
+    fn encrypted_results_keep_their_key_format() {
+        let identity = Identity::generate();
+        let (mut result, _) = result();
+        result.private_key = SecretString::new("00ff".to_string());
+        result.format = KeyFormat::Hex;
+
+        let sealed = result.encrypt_to(&identity.to_public()).unwrap();
+        assert_eq!(sealed.format, KeyFormat::Base58);
+        let opened = sealed.decrypt_with(&[identity]).unwrap();
+        assert_eq!(opened.format, KeyFormat::Hex);
+        assert_eq!(opened.private_key.expose(), "00ff");
+    }
+
+    #[test]
+    fn results_need_the_right_identity() {
+        let (result, _) = result();
+        let sealed = result
+            .encrypt_to(&Identity::generate().to_public())
+            .unwrap();
+        assert!(sealed.decrypt_with(&[Identity::generate()]).is_err());
+    }
+}
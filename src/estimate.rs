@@ -0,0 +1,211 @@
+use crate::generator::VanityRequest;
+use crate::keygen::KeyStream;
+use crate::matcher::{Matcher, BASE58_ALPHABET};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// Public keys are 32 bytes
+const KEY_BITS: i32 = 256;
+
+/// How long a search is likely to take at a given key generation rate.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Estimate {
+    /// Mean number of random keypairs needed per match, or `None` when the
+    /// pattern is a glob or regular expression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_attempts: Option<f64>,
+    /// Number of matches the search collects.
+    pub count: usize,
+    /// Key generation rate the estimate is based on.
+    pub keys_per_second: f64,
+    /// Mean time to collect every match at `keys_per_second`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_seconds: Option<f64>,
+}
+
+impl Estimate {
+    /// Estimates collecting `count` matches for `matcher` at `keys_per_second`.
+    pub fn new(matcher: &Matcher, count: usize, keys_per_second: f64) -> Self {
+        let expected_attempts = matcher.expected_attempts();
+        let expected_seconds = expected_attempts
+            .filter(|_| keys_per_second > 0.0)
+            .map(|expected| expected * count as f64 / keys_per_second);
+
+        Self {
+            expected_attempts,
+            count,
+            keys_per_second,
+            expected_seconds,
+        }
+    }
+}
+
+/// Measures how many candidate keys per second this machine derives and encodes
+/// across all cores for searches like `request`, by grinding for `duration`.
+/// Seed phrase searches run far slower than bare keys.
+///
+/// Blocks the calling thread for `duration`.
+pub fn measure_keys_per_second(request: &VanityRequest, duration: Duration) -> f64 {
+    let attempts = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+    let start = Instant::now();
+
+    let workers = num_cpus::get();
+    (0..workers).into_par_iter().for_each(|worker| {
+        let mut keys = KeyStream::new(request, worker, workers);
+        let mut local = 0;
+        while !done.load(Ordering::Relaxed) {
+            let (pubkey, _) = keys.next_candidate();
+            std::hint::black_box(bs58::encode(pubkey).into_string());
+            local += 1;
+            if start.elapsed() >= duration {
+                done.store(true, Ordering::Relaxed);
+            }
+        }
+        attempts.fetch_add(local, Ordering::Relaxed);
+    });
+
+    attempts.into_inner() as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Probability that a random address starts with exactly `prefix`
+/// (case-sensitive).
+///
+/// Base58 addresses of 32-byte keys are not uniform at the front: most are 44
+/// characters long and can only start with `2`..`J`, while the rarer
+/// 43-character ones spread over the whole alphabet. Each leading `1` stands
+/// for a zero byte.
+pub fn prefix_probability(prefix: &str) -> f64 {
+    let ones = prefix.bytes().take_while(|&b| b == b'1').count();
+    let digits: Option<Vec<u8>> = prefix[ones..]
+        .chars()
+        .map(|c| BASE58_ALPHABET.find(c).map(|digit| digit as u8))
+        .collect();
+    let Some(digits) = digits else {
+        return 0.0;
+    };
+    if ones > 32 {
+        return 0.0;
+    }
+
+    // The key is `ones` zero bytes followed by a value in [floor, total):
+    // its top byte is not zero, so it encodes without any further leading '1'
+    let bits = KEY_BITS - 8 * ones as i32;
+    let zero_bytes = 2f64.powi(-8 * ones as i32);
+    if digits.is_empty() {
+        // Further zero bytes only add more '1's
+        return zero_bytes;
+    }
+    if bits == 0 {
+        return 0.0;
+    }
+    let total = power_of_two(bits as usize);
+    let floor = power_of_two(bits as usize - 8);
+
+    // Values of one encoded length that start with `prefix` form a run of
+    // 58^(length - len) numbers. Only the runs at the shortest and longest
+    // lengths can be clipped by `floor` and `total`, so compare digits
+    // exactly there and keep floats for the sizes.
+    let len = digits.len();
+    let mut probability = 0.0;
+    for length in len.max(floor.len())..=total.len() {
+        let mut start = 0.0;
+        let mut end = 1.0;
+        if floor.len() == length {
+            match floor[..len].cmp(&digits[..]) {
+                std::cmp::Ordering::Greater => continue,
+                std::cmp::Ordering::Equal => start = fraction(&floor[len..]),
+                std::cmp::Ordering::Less => {}
+            }
+        }
+        if total.len() == length {
+            match total[..len].cmp(&digits[..]) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => end = fraction(&total[len..]),
+                std::cmp::Ordering::Greater => {}
+            }
+        }
+        if end > start {
+            let log_run = (length - len) as f64 * 58f64.ln() - bits as f64 * 2f64.ln();
+            probability += (end - start) * log_run.exp();
+        }
+    }
+
+    zero_bytes * probability
+}
+
+// Base58 digits of 2^exponent, most significant first. Only whole bytes up
+// to a full key are ever needed, so they are computed once.
+fn power_of_two(exponent: usize) -> &'static [u8] {
+    static POWERS: OnceLock<Vec<Vec<u8>>> = OnceLock::new();
+    let powers = POWERS.get_or_init(|| {
+        let mut digits = vec![1u8];
+        let mut powers = Vec::new();
+        for bit in 0..=KEY_BITS {
+            if bit % 8 == 0 {
+                powers.push(digits.iter().rev().copied().collect());
+            }
+            let mut carry = 0;
+            for digit in digits.iter_mut() {
+                let doubled = *digit * 2 + carry;
+                *digit = doubled % 58;
+                carry = doubled / 58;
+            }
+            if carry > 0 {
+                digits.push(carry);
+            }
+        }
+        powers
+    });
+    &powers[exponent / 8]
+}
+
+// Reads base58 digits as the fractional part 0.d1d2d3...
+fn fraction(digits: &[u8]) -> f64 {
+    digits
+        .iter()
+        .rev()
+        .fold(0.0, |fraction, &digit| (fraction + digit as f64) / 58.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum_of_extensions(prefix: &str) -> f64 {
+        BASE58_ALPHABET
+            .chars()
+            .map(|c| prefix_probability(&format!("{}{}", prefix, c)))
+            .sum()
+    }
+
+    #[test]
+    fn prefix_probabilities_sum_to_one() {
+        let total = sum_of_extensions("");
+        assert!((total - 1.0).abs() < 1e-9, "{}", total);
+    }
+
+    #[test]
+    fn extensions_share_out_their_prefix() {
+        for prefix in ["1", "11", "2", "J", "z", "1z", "So"] {
+            let expected = prefix_probability(prefix);
+            let total = sum_of_extensions(prefix);
+            assert!(
+                (total - expected).abs() <= expected * 1e-9,
+                "{}: {} vs {}",
+                prefix,
+                total,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn characters_outside_the_alphabet_never_match() {
+        assert_eq!(prefix_probability("0"), 0.0);
+        assert_eq!(prefix_probability("1l"), 0.0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Job progress pushed as server-sent events.
+//!
+//! `GET /jobs/{id}/events` answers with a `text/event-stream` rather than
+//! making clients poll `/status`. Every event carries the same JSON as a
+//! `/status` response, except `result` events, which carry one new match.
+//!
+//! Event payloads can hold private keys, so the parser keeps them in
+//! buffers that are wiped when dropped, and clients deserialize them
+//! straight into [`JobStatus`] and [`VanityResult`].
+
+use crate::generator::{SearchStats, VanityResult};
+use crate::matcher::PatternError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+/// What a job event reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobEventKind {
+    /// The job changed state, e.g. from queued to running. Also sent first.
+    Status,
+    /// Attempts and rate, about once a second while the job waits or runs.
+    Progress,
+    /// A match, as soon as it is found.
+    Result,
+    /// The final status. The stream ends after it.
+    Done,
+}
+
+impl JobEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobEventKind::Status => "status",
+            JobEventKind::Progress => "progress",
+            JobEventKind::Result => "result",
+            JobEventKind::Done => "done",
+        }
+    }
+}
+
+impl fmt::Display for JobEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(JobEventKind::Status),
+            "progress" => Ok(JobEventKind::Progress),
+            "result" => Ok(JobEventKind::Result),
+            "done" => Ok(JobEventKind::Done),
+            _ => Err(format!(
+                "Job event must be 'status', 'progress', 'result' or 'done', not '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// One event of a job's stream, with its JSON payload.
+#[derive(Clone)]
+pub struct JobEvent {
+    pub kind: JobEventKind,
+    pub data: Zeroizing<String>,
+}
+
+/// A `/status` response, or the payload of any job event but `result`, as
+/// a client reads it.
+#[derive(Debug, Default, Deserialize)]
+pub struct JobStatus {
+    /// "pending", "running", "complete" or "error".
+    pub status: String,
+    /// Place in the server's queue while the job is pending, from 1.
+    #[serde(default)]
+    pub queue_position: Option<usize>,
+    #[serde(default)]
+    pub found: usize,
+    #[serde(default)]
+    pub requested: usize,
+    #[serde(flatten)]
+    pub stats: SearchStats,
+    /// The first match, once a job is complete.
+    #[serde(default)]
+    pub result: Option<VanityResult>,
+    /// Every match so far, for multi-address jobs.
+    #[serde(default)]
+    pub results: Option<Vec<VanityResult>>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl JobEvent {
+    pub fn new(kind: JobEventKind, data: &impl Serialize) -> Self {
+        Self {
+            kind,
+            data: Zeroizing::new(serde_json::to_string(data).unwrap()),
+        }
+    }
+
+    /// Deserializes the payload, e.g. into a [`JobStatus`] or, for a
+    /// `result` event, a [`VanityResult`].
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_str(&self.data).map_err(|e| format!("Invalid {} event: {}", self.kind, e))
+    }
+
+    /// The event as it goes over the wire.
+    pub fn to_sse(&self) -> String {
+        format!("event: {}\ndata: {}\n\n", self.kind, self.data.as_str())
+    }
+}
+
+impl fmt::Debug for JobEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The payload may hold private keys
+        f.debug_struct("JobEvent")
+            .field("kind", &self.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The body of a failed request, e.g. a `400` for a pattern that can never
+/// match or a `503` while the job queue is full, as a client reads it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiError {
+    pub error: String,
+    /// Where a pattern goes wrong, when that is why the request failed.
+    #[serde(flatten)]
+    pub pattern: Option<PatternError>,
+}
+
+impl ApiError {
+    /// The message, followed for pattern errors by the pattern with a caret
+    /// under the offending character.
+    pub fn describe(&self) -> String {
+        match &self.pattern {
+            Some(pattern) => format!(
+                "{}\n  {}\n  {}^",
+                self.error,
+                pattern.pattern,
+                " ".repeat(pattern.index)
+            ),
+            None => self.error.clone(),
+        }
+    }
+}
+
+impl JobStatus {
+    /// Every match reported: `results` for multi-address jobs, otherwise
+    /// `result`.
+    pub fn into_results(self) -> Vec<VanityResult> {
+        match self.results {
+            Some(results) => results,
+            None => self.result.into_iter().collect(),
+        }
+    }
+}
+
+/// Reassembles events from the chunks of a `text/event-stream` body, which
+/// may split an event anywhere.
+pub struct EventParser {
+    buffer: Zeroizing<Vec<u8>>,
+}
+
+impl EventParser {
+    pub fn new() -> Self {
+        Self {
+            buffer: Zeroizing::new(Vec::new()),
+        }
+    }
+
+    /// Adds a chunk and returns the events it completes. Comments and
+    /// events of unknown kinds are skipped.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<JobEvent> {
+        // Grow the buffer by hand, so a reallocation doesn't leave an
+        // unwiped copy of what it held behind
+        let needed = self.buffer.len() + chunk.len();
+        if needed > self.buffer.capacity() {
+            let capacity = needed.max(self.buffer.capacity() * 2);
+            let mut grown = Zeroizing::new(Vec::with_capacity(capacity));
+            grown.extend_from_slice(&self.buffer);
+            self.buffer = grown;
+        }
+        self.buffer
+            .extend(chunk.iter().filter(|&&byte| byte != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|pair| pair == b"\n\n") {
+            let block: Zeroizing<Vec<u8>> = Zeroizing::new(self.buffer.drain(..end + 2).collect());
+            let Ok(block) = std::str::from_utf8(&block) else {
+                continue;
+            };
+
+            let mut kind = None;
+            let mut data = Vec::new();
+            for line in block.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    kind = value.trim().parse().ok();
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data.push(value.strip_prefix(' ').unwrap_or(value));
+                }
+            }
+            if let Some(kind) = kind {
+                events.push(JobEvent {
+                    kind,
+                    data: Zeroizing::new(data.join("\n")),
+                });
+            }
+        }
+        events
+    }
+}
+
+impl Default for EventParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_errors_point_at_the_character() {
+        let body = r#"{"error":"Pattern 's0l': character '0' at position 2 is not in the base58 alphabet, which leaves out 0, O, I and l","pattern":"s0l","index":1,"character":"0","reason":"is not in the base58 alphabet, which leaves out 0, O, I and l"}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+        let pattern = error.pattern.as_ref().unwrap();
+        assert_eq!((pattern.index, pattern.character), (1, '0'));
+        assert!(error.describe().ends_with("\n  s0l\n   ^"));
+    }
+
+    #[test]
+    fn other_errors_are_shown_as_is() {
+        let body = r#"{"error":"The job queue is full (100 jobs waiting); try again later"}"#;
+        let error: ApiError = serde_json::from_str(body).unwrap();
+        assert!(error.pattern.is_none());
+        assert_eq!(
+            error.describe(),
+            "The job queue is full (100 jobs waiting); try again later"
+        );
+    }
+
+    fn parse_chunks(chunks: &[&[u8]]) -> Vec<(JobEventKind, String)> {
+        let mut parser = EventParser::new();
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk))
+            .map(|event| (event.kind, event.data.as_str().to_string()))
+            .collect()
+    }
+
+    fn event(kind: JobEventKind, data: &str) -> (JobEventKind, String) {
+        (kind, data.to_string())
+    }
+
+    #[test]
+    fn events_split_anywhere_come_out_whole() {
+        let stream = concat!(
+            "event: status\ndata: {\"status\":\"running\"}\n\n",
+            "event: done\ndata: {\"status\":\"complete\"}\n\n"
+        )
+        .as_bytes();
+        let expected = vec![
+            event(JobEventKind::Status, r#"{"status":"running"}"#),
+            event(JobEventKind::Done, r#"{"status":"complete"}"#),
+        ];
+        for split in 0..=stream.len() {
+            let (first, rest) = stream.split_at(split);
+            assert_eq!(parse_chunks(&[first, rest]), expected, "split at {}", split);
+        }
+
+        // One byte at a time
+        let bytes: Vec<&[u8]> = stream.chunks(1).collect();
+        assert_eq!(parse_chunks(&bytes), expected);
+    }
+
+    #[test]
+    fn crlf_line_endings_are_accepted() {
+        let stream = b"event: progress\r\ndata: {}\r\n\r\n";
+        for split in 0..=stream.len() {
+            let (first, rest) = stream.split_at(split);
+            assert_eq!(
+                parse_chunks(&[first, rest]),
+                [event(JobEventKind::Progress, "{}")],
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[test]
+    fn data_lines_are_joined_with_newlines() {
+        let stream = b"event: result\ndata: {\"public_key\":\ndata:\"abc\"}\ndata:\n\n";
+        assert_eq!(
+            parse_chunks(&[stream]),
+            [event(JobEventKind::Result, "{\"public_key\":\n\"abc\"}\n")]
+        );
+    }
+
+    #[test]
+    fn comments_and_unknown_kinds_are_skipped() {
+        let stream = concat!(
+            ": keep-alive\n\n",
+            "event: heartbeat\ndata: {}\n\n",
+            "data: no event line\n\n",
+            ": a comment inside an event\nevent: done\nid: 7\ndata: {}\n\n"
+        );
+        assert_eq!(
+            parse_chunks(&[stream.as_bytes()]),
+            [event(JobEventKind::Done, "{}")]
+        );
+    }
+
+    #[test]
+    fn events_read_back_what_they_write() {
+        let sent = JobEvent::new(
+            JobEventKind::Progress,
+            &serde_json::json!({"status": "running", "found": 1, "requested": 2, "attempts": 500, "keys_per_second": 250.0}),
+        );
+        let received = EventParser::new().feed(sent.to_sse().as_bytes());
+        assert_eq!(received.len(), 1);
+        let status: JobStatus = received[0].parse().unwrap();
+        assert_eq!((status.found, status.requested), (1, 2));
+        assert_eq!(status.stats.attempts, 500);
+    }
+}
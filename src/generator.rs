@@ -1,88 +1,971 @@
+use crate::derived::DerivedAccountOptions;
+use crate::keygen::{KeyStream, MnemonicOptions};
+use crate::matcher::{MatchMode, Matcher};
+use crate::pda::{PdaOptions, ProgramAddress};
+use crate::secret::SecretString;
+use crate::split::SplitKeyOptions;
+use crate::token::TokenAccountOptions;
+use log::{debug, error, info};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, Signer};
-use serde::{Serialize, Deserialize};
-use log::{info, debug, error};
-use tokio::sync::watch;
-use tokio::time::timeout;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
-use bs58;
+use tokio::sync::watch;
+use tokio::time::sleep;
+use zeroize::Zeroizing;
+
+/// Attempts each worker makes before publishing them to the shared counter.
+const ATTEMPT_BATCH: u64 = 256;
+
+/// Longest a worker above its search's [`WorkerLimit`] stays parked before
+/// checking whether the search was canceled.
+const PAUSED_WORKER_POLL: Duration = Duration::from_millis(250);
 
+/// Shortest pattern the bundled clients will submit.
+pub const MIN_PATTERN_LEN: usize = 3;
+/// Longest pattern the bundled clients will submit.
+pub const MAX_PATTERN_LEN: usize = 8;
+
+/// Most matching addresses one search will collect.
+pub const MAX_COUNT: usize = 1000;
+
+/// What to search for: a pattern, how to interpret it and where it must
+/// appear in the address.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VanityRequest {
     pub pattern: String,
+    /// Further candidates searched in the same pass; an address matching any
+    /// of `pattern` or `patterns` is accepted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
     pub position: VanityPosition,
+    #[serde(default)]
+    pub mode: MatchMode,
+    /// Required ending when `position` is [`VanityPosition::Both`]; `pattern`
+    /// is then the required beginning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    /// Require the exact letter case of the pattern. By default `sol` also
+    /// matches `SOL` or `SoL`.
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// How many matching keypairs to collect before the search stops.
+    #[serde(default = "default_count")]
+    pub count: usize,
+    /// Draw BIP39 seed phrases instead of bare random keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<MnemonicOptions>,
+    /// Search program derived addresses instead of keypairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pda: Option<PdaOptions>,
+    /// Search `create_with_seed` accounts of a base key instead of keypairs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived_account: Option<DerivedAccountOptions>,
+    /// Match each wallet's associated token account for a mint instead of
+    /// the wallet address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_account: Option<TokenAccountOptions>,
+    /// Search offsets to a client's public point, so the private key never
+    /// exists here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_key: Option<SplitKeyOptions>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+fn default_count() -> usize {
+    1
+}
+
+/// Attempt statistics for a running or finished search.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct SearchStats {
+    /// Keypairs generated so far.
+    pub attempts: u64,
+    /// Average generation rate since the search started.
+    pub keys_per_second: f64,
+    /// Mean number of attempts per match, when the pattern allows estimating it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_attempts: Option<f64>,
+    /// Expected seconds until the outstanding matches are found at the
+    /// current rate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eta_seconds: Option<f64>,
+}
+
+/// Where in the base58 address the pattern must appear.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum VanityPosition {
     Prefix,
     Suffix,
+    /// Starts with the pattern and ends with the request's `suffix`.
+    Both,
 }
 
+/// A matching address and what it was derived from.
+pub struct VanityMatch {
+    pub address: Pubkey,
+    /// Keypair of the address, or of `owner` when set; `None` for program
+    /// derived addresses.
+    pub keypair: Option<Keypair>,
+    /// Seed phrase the keypair came from, for mnemonic searches.
+    pub mnemonic: Option<SecretString>,
+    /// Seeds and bump, for program derived address searches.
+    pub program_address: Option<ProgramAddress>,
+    /// `create_with_seed` seed, for derived account searches.
+    pub derived_seed: Option<String>,
+    /// Wallet whose associated token account is `address`, for token account
+    /// searches.
+    pub owner: Option<Pubkey>,
+    /// Little-endian scalar to add to the client's secret, for split-key
+    /// searches.
+    pub offset: Option<[u8; 32]>,
+}
+
+/// A matching address and its 64-byte secret key, base58-encoded unless
+/// `format` says otherwise. Program derived addresses have no secret key
+/// and carry their seeds instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VanityResult {
     pub public_key: String,
-    pub private_key: String,
+    #[serde(default, skip_serializing_if = "SecretString::is_empty")]
+    pub private_key: SecretString,
+    /// How `private_key` is encoded.
+    #[serde(default, skip_serializing_if = "KeyFormat::is_base58")]
+    pub format: KeyFormat,
+    /// Which of the request's candidate patterns the address matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_pattern: Option<String>,
+    /// Seed phrase that restores the keypair, for mnemonic searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<SecretString>,
+    /// Hex-encoded seeds of a program derived address, without the bump.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seeds: Vec<String>,
+    /// Canonical bump of a program derived address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump: Option<u8>,
+    /// Value of the varied seed, when a PDA search counted it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter: Option<u64>,
+    /// Seed that `Pubkey::create_with_seed` turns into the address, for
+    /// derived account searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+    /// The matching associated token account of `public_key`, for token
+    /// account searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_account: Option<String>,
+    /// Hex offset that [`crate::SplitKey::combine`] adds to the client's
+    /// secret, for split-key searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+    /// ASCII-armored age file holding `private_key` and `mnemonic`, when the
+    /// result was sealed to the client's key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<String>,
 }
 
-pub async fn generate_with_timeout(
-    req: VanityRequest,
-    timeout_secs: u64,
-    mut cancel_rx: watch::Receiver<bool>,
-) -> Result<VanityResult, String> {
-    if req.pattern.len() < 1 {
-        return Err("Pattern must be at least 1 character long".to_string());
-    }
-    
-    debug!("Starting vanity address generation for pattern: {}", req.pattern);
-    
-    let timeout_fut = timeout(Duration::from_secs(timeout_secs), async {
-        let mut attempts = 0;
-        loop {
-            if *cancel_rx.borrow() {
-                debug!("Generation cancelled after {} attempts", attempts);
-                return Err("Generation cancelled".to_string());
+/// Encoding of a 64-byte secret key (32-byte seed followed by the public
+/// key).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyFormat {
+    /// A single base58 string, as wallets import it.
+    #[default]
+    Base58,
+    /// The `[u8; 64]` JSON array `solana-keygen` writes to keypair files.
+    JsonArray,
+    /// Lowercase hexadecimal.
+    Hex,
+}
+
+impl VanityRequest {
+    pub fn new(pattern: impl Into<String>, position: VanityPosition) -> Self {
+        Self {
+            pattern: pattern.into(),
+            patterns: Vec::new(),
+            position,
+            mode: MatchMode::Exact,
+            suffix: None,
+            case_sensitive: false,
+            count: 1,
+            mnemonic: None,
+            pda: None,
+            derived_account: None,
+            token_account: None,
+            split_key: None,
+        }
+    }
+
+    /// Adds alternative patterns to search for alongside `pattern`.
+    pub fn with_patterns<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.patterns.extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Also requires the address to end with `suffix`. Only valid with
+    /// [`VanityPosition::Both`].
+    pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.suffix = Some(suffix.into());
+        self
+    }
+
+    /// Interprets the pattern as a glob or regular expression instead of
+    /// a literal.
+    pub fn with_mode(mut self, mode: MatchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Requires the letter case of the pattern to match exactly.
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Collects `count` matching keypairs instead of stopping at the first.
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Generates restorable seed phrases instead of bare keypairs.
+    pub fn with_mnemonic(mut self, mnemonic: MnemonicOptions) -> Self {
+        self.mnemonic = Some(mnemonic);
+        self
+    }
+
+    /// Searches program derived addresses instead of keypairs.
+    pub fn with_pda(mut self, pda: PdaOptions) -> Self {
+        self.pda = Some(pda);
+        self
+    }
+
+    /// Searches seeds for `create_with_seed` accounts instead of keypairs.
+    pub fn with_derived_account(mut self, derived_account: DerivedAccountOptions) -> Self {
+        self.derived_account = Some(derived_account);
+        self
+    }
+
+    /// Matches wallets by their associated token account for a mint.
+    pub fn with_token_account(mut self, token_account: TokenAccountOptions) -> Self {
+        self.token_account = Some(token_account);
+        self
+    }
+
+    /// Searches offsets to a client's public point instead of keypairs.
+    pub fn with_split_key(mut self, split_key: SplitKeyOptions) -> Self {
+        self.split_key = Some(split_key);
+        self
+    }
+
+    /// The rest of this search after `found` have already been recorded:
+    /// the outstanding count, with counter seeds starting past the highest
+    /// one that matched so no match turns up twice.
+    pub fn resume_after(&self, found: &[VanityResult]) -> Self {
+        let mut request = self
+            .clone()
+            .with_count(self.count.saturating_sub(found.len()));
+        if let Some(pda) = &mut request.pda {
+            let highest = found.iter().filter_map(|result| result.counter).max();
+            if let Some(highest) = highest {
+                pda.start_counter = pda.start_counter.max(highest.wrapping_add(1));
+            }
+        }
+        if let Some(derived_account) = &mut request.derived_account {
+            let highest = found
+                .iter()
+                .filter_map(|result| derived_account.counter(result.seed.as_deref()?))
+                .max();
+            if let Some(highest) = highest {
+                derived_account.start_counter =
+                    derived_account.start_counter.max(highest.wrapping_add(1));
             }
+        }
+        request
+    }
+
+    /// `pattern` followed by any alternatives from `patterns`.
+    pub fn candidates(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.pattern.as_str()).chain(self.patterns.iter().map(String::as_str))
+    }
+
+    /// Rejects requests that can never be searched.
+    pub fn validate(&self) -> Result<(), String> {
+        Matcher::new(self).map(|_| ())
+    }
+}
 
-            attempts += 1;
-            if attempts % 1000 == 0 {
-                debug!("Made {} attempts", attempts);
+impl VanityPosition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VanityPosition::Prefix => "prefix",
+            VanityPosition::Suffix => "suffix",
+            VanityPosition::Both => "both",
+        }
+    }
+}
+
+impl fmt::Display for VanityPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for VanityRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let candidates = self
+            .candidates()
+            .map(|pattern| format!("'{}'", pattern))
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        match (self.position, &self.suffix) {
+            (VanityPosition::Both, Some(suffix)) => {
+                write!(f, "prefix {} and suffix '{}'", candidates, suffix)
             }
+            (position, _) => write!(f, "{} {}", position, candidates),
+        }
+    }
+}
 
-            let keypair = Keypair::new();
-            let address = keypair.pubkey().to_string();
-            
-            let matches = match req.position {
-                VanityPosition::Prefix => {
-                    let prefix = &address[0..req.pattern.len()];
-                    debug!("Checking prefix: {} against pattern: {}", prefix, req.pattern);
-                    prefix.eq_ignore_ascii_case(&req.pattern)
-                },
-                VanityPosition::Suffix => {
-                    let start = address.len() - req.pattern.len();
-                    let suffix = &address[start..];
-                    debug!("Checking suffix: {} against pattern: {}", suffix, req.pattern);
-                    suffix.eq_ignore_ascii_case(&req.pattern)
-                },
-            };
-
-            if matches {
-                info!("Found matching address after {} attempts: {}", attempts, address);
-                let result = VanityResult {
-                    public_key: address,
-                    private_key: bs58::encode(keypair.to_bytes()).into_string(),
-                };
-                debug!("Generated result: {:?}", result);
-                return Ok(result);
+impl KeyFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyFormat::Base58 => "base58",
+            KeyFormat::JsonArray => "json-array",
+            KeyFormat::Hex => "hex",
+        }
+    }
+
+    fn is_base58(&self) -> bool {
+        *self == KeyFormat::Base58
+    }
+
+    /// Encodes raw secret key bytes.
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            KeyFormat::Base58 => bs58::encode(bytes).into_string(),
+            KeyFormat::JsonArray => serde_json::to_string(bytes).unwrap(),
+            KeyFormat::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        }
+    }
+
+    /// Decodes a secret key written by [`KeyFormat::encode`].
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text = text.trim();
+        match self {
+            KeyFormat::Base58 => bs58::decode(text).into_vec().map_err(|e| e.to_string()),
+            KeyFormat::JsonArray => serde_json::from_str(text).map_err(|e| e.to_string()),
+            KeyFormat::Hex => {
+                if !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err("Hex key must be an even number of hex digits".to_string());
+                }
+                (0..text.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| e.to_string()))
+                    .collect()
             }
         }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for KeyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base58" => Ok(KeyFormat::Base58),
+            "json-array" | "json" => Ok(KeyFormat::JsonArray),
+            "hex" => Ok(KeyFormat::Hex),
+            _ => Err("Format must be 'json-array', 'base58' or 'hex'".to_string()),
+        }
+    }
+}
+
+impl FromStr for VanityPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "prefix" => Ok(VanityPosition::Prefix),
+            "suffix" => Ok(VanityPosition::Suffix),
+            "both" => Ok(VanityPosition::Both),
+            _ => Err("Position must be 'prefix', 'suffix' or 'both'".to_string()),
+        }
+    }
+}
+
+impl SearchStats {
+    /// Derives the rate and ETA from `attempts` made over `elapsed` while
+    /// `remaining` matches are still outstanding.
+    pub fn new(
+        attempts: u64,
+        elapsed: Duration,
+        expected_attempts: Option<f64>,
+        remaining: usize,
+    ) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        let keys_per_second = if seconds > 0.0 {
+            attempts as f64 / seconds
+        } else {
+            0.0
+        };
+        // Every keypair is an independent draw, so the wait for the next match
+        // doesn't shrink with the attempts already spent
+        let eta_seconds = expected_attempts
+            .filter(|_| keys_per_second > 0.0)
+            .map(|expected| expected * remaining as f64 / keys_per_second);
+
+        Self {
+            attempts,
+            keys_per_second,
+            expected_attempts,
+            eta_seconds,
+        }
+    }
+}
+
+impl VanityResult {
+    /// Records which of the matcher's candidate patterns this address hit.
+    pub fn with_match(mut self, matcher: &Matcher) -> Self {
+        let address = self.token_account.as_ref().unwrap_or(&self.public_key);
+        self.matched_pattern = matcher.matched_pattern(address).map(str::to_string);
+        self
+    }
+
+    /// The raw 64-byte secret key, whatever its current encoding.
+    pub fn secret_bytes(&self) -> Result<Zeroizing<Vec<u8>>, String> {
+        self.format
+            .decode(self.private_key.expose())
+            .map(Zeroizing::new)
+    }
+
+    /// Re-encodes `private_key` in `format`.
+    pub fn with_format(mut self, format: KeyFormat) -> Result<Self, String> {
+        if format != self.format && !self.private_key.is_empty() {
+            self.private_key = format.encode(&self.secret_bytes()?).into();
+            self.format = format;
+        }
+        Ok(self)
+    }
+}
+
+impl From<&Keypair> for VanityResult {
+    fn from(keypair: &Keypair) -> Self {
+        Self::from(&VanityMatch {
+            address: keypair.pubkey(),
+            keypair: Some(keypair.insecure_clone()),
+            mnemonic: None,
+            program_address: None,
+            derived_seed: None,
+            owner: None,
+            offset: None,
+        })
+    }
+}
+
+// Shows the keypair by its public key; `Keypair`'s own `Debug` prints the
+// secret key bytes
+impl fmt::Debug for VanityMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VanityMatch")
+            .field("address", &self.address)
+            .field("keypair", &self.keypair.as_ref().map(Keypair::pubkey))
+            .field("mnemonic", &self.mnemonic)
+            .field("program_address", &self.program_address)
+            .field("derived_seed", &self.derived_seed)
+            .field("owner", &self.owner)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl From<&VanityMatch> for VanityResult {
+    fn from(found: &VanityMatch) -> Self {
+        let mut result = Self {
+            public_key: found.address.to_string(),
+            private_key: SecretString::default(),
+            format: KeyFormat::Base58,
+            matched_pattern: None,
+            mnemonic: found.mnemonic.clone(),
+            seeds: Vec::new(),
+            bump: None,
+            counter: None,
+            seed: found.derived_seed.clone(),
+            token_account: None,
+            offset: found.offset.map(|offset| KeyFormat::Hex.encode(&offset)),
+            encrypted: None,
+        };
+        // A token account search matched the wallet's token account
+        if let Some(owner) = &found.owner {
+            result.public_key = owner.to_string();
+            result.token_account = Some(found.address.to_string());
+        }
+        if let Some(keypair) = &found.keypair {
+            let bytes = Zeroizing::new(keypair.to_bytes());
+            result.private_key = bs58::encode(bytes.as_ref()).into_string().into();
+        }
+        if let Some(program_address) = &found.program_address {
+            result.seeds = program_address
+                .seeds
+                .iter()
+                .map(|seed| KeyFormat::Hex.encode(seed))
+                .collect();
+            result.bump = Some(program_address.bump);
+            result.counter = program_address.counter;
+        }
+        result
+    }
+}
+
+/// Grinds keypairs on every core until one matches `request`, ignoring
+/// `request.count`. Use [`find_vanity_addresses`] to also get the seed phrase
+/// of a mnemonic search.
+///
+/// Blocks the calling thread. Setting `cancel_flag` makes every worker stop
+/// and the search return an error.
+pub fn find_vanity_address(
+    request: &VanityRequest,
+    cancel_flag: &AtomicBool,
+) -> Result<Keypair, String> {
+    find_first(request, cancel_flag)?
+        .keypair
+        .ok_or_else(|| "Derived addresses have no keypair".to_string())
+}
+
+// The first match, seed phrase included
+fn find_first(request: &VanityRequest, cancel_flag: &AtomicBool) -> Result<VanityMatch, String> {
+    let single = request.clone().with_count(1);
+    find_vanity_addresses(&single, cancel_flag, &AtomicU64::new(0), |_| {})
+        .map(|mut found| found.remove(0))
+}
+
+/// Grinds keypairs on every core until `request.count` of them match.
+///
+/// Searches with [`VanityRequest::mnemonic`] set return the seed phrase of
+/// each match as well. Each match is handed to `on_match` as soon as it is
+/// found, from whichever worker thread found it, and `attempts` is kept up
+/// to date with the number of keypairs tried.
+///
+/// Blocks the calling thread. Setting `cancel_flag` makes every worker stop
+/// and the search return an error.
+pub fn find_vanity_addresses<F>(
+    request: &VanityRequest,
+    cancel_flag: &AtomicBool,
+    attempts: &AtomicU64,
+    on_match: F,
+) -> Result<Vec<VanityMatch>, String>
+where
+    F: Fn(&VanityMatch) + Sync,
+{
+    find_vanity_addresses_limited(
+        request,
+        cancel_flag,
+        attempts,
+        &WorkerLimit::all_cores(),
+        on_match,
+    )
+}
+
+/// How many worker threads a search may keep busy. It can be changed while
+/// the search runs, e.g. to share the cores between several searches.
+#[derive(Debug)]
+pub struct WorkerLimit {
+    workers: AtomicUsize,
+    capacity: usize,
+    // Paused workers park here until the limit is raised
+    lock: Mutex<()>,
+    raised: Condvar,
+}
+
+impl WorkerLimit {
+    /// A fixed limit of `workers`.
+    pub fn new(workers: usize) -> Self {
+        Self::with_capacity(workers, workers)
+    }
+
+    /// Starts at `workers` and can later be raised up to `capacity`.
+    pub fn with_capacity(workers: usize, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            workers: AtomicUsize::new(workers.clamp(1, capacity)),
+            capacity,
+            lock: Mutex::new(()),
+            raised: Condvar::new(),
+        }
+    }
+
+    /// One worker per core.
+    pub fn all_cores() -> Self {
+        Self::new(num_cpus::get())
+    }
+
+    pub fn get(&self) -> usize {
+        self.workers.load(Ordering::Relaxed)
+    }
+
+    /// The most workers the limit can ever allow. A search starts this many
+    /// threads and parks those above the current limit.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// At least one worker always keeps going, and at most `capacity`.
+    pub fn set(&self, workers: usize) {
+        self.workers
+            .store(workers.clamp(1, self.capacity), Ordering::Relaxed);
+        self.wake();
+    }
+
+    // Wakes the parked workers to look at the limit and their search again
+    fn wake(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.raised.notify_all();
+    }
+
+    // Parks worker `worker` while the limit leaves it out, for at most
+    // `PAUSED_WORKER_POLL`
+    fn pause(&self, worker: usize) {
+        let guard = self.lock.lock().unwrap();
+        let _ = self
+            .raised
+            .wait_timeout_while(guard, PAUSED_WORKER_POLL, |_| worker >= self.get())
+            .unwrap();
+    }
+}
+
+/// [`find_vanity_addresses`] with at most `limit` workers busy at a time.
+///
+/// The search runs [`WorkerLimit::capacity`] workers on its own thread pool,
+/// so searches running side by side don't queue behind each other's
+/// workers. Workers numbered at or above the limit stay parked until it is
+/// raised.
+pub fn find_vanity_addresses_limited<F>(
+    request: &VanityRequest,
+    cancel_flag: &AtomicBool,
+    attempts: &AtomicU64,
+    limit: &WorkerLimit,
+    on_match: F,
+) -> Result<Vec<VanityMatch>, String>
+where
+    F: Fn(&VanityMatch) + Sync,
+{
+    let matcher = Matcher::new(request)?;
+    let found = Mutex::new(Vec::new());
+    let done = AtomicBool::new(false);
+
+    let workers = limit.capacity();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .build()
+        .map_err(|e| format!("Couldn't start the search workers: {}", e))?;
+
+    // Generate keypairs in parallel
+    pool.install(|| {
+        (0..workers).into_par_iter().for_each(|worker| {
+            let mut keys = KeyStream::new(request, worker, workers);
+            let mut unreported = 0;
+            while !cancel_flag.load(Ordering::Relaxed) && !done.load(Ordering::Relaxed) {
+                if worker >= limit.get() {
+                    limit.pause(worker);
+                    continue;
+                }
+                let (pubkey, secret) = keys.next_candidate();
+                // Publish in batches so the workers don't contend on the counter
+                unreported += 1;
+                if unreported == ATTEMPT_BATCH {
+                    attempts.fetch_add(unreported, Ordering::Relaxed);
+                    unreported = 0;
+                }
+                // Only encode the keys whose raw bytes could match
+                if matcher.may_match(&pubkey)
+                    && matcher.is_match(&bs58::encode(pubkey).into_string())
+                {
+                    let Some(matched) = keys.finish(pubkey, secret) else {
+                        error!(
+                            "Dropped a match at {}: its keypair doesn't control it",
+                            bs58::encode(pubkey).into_string()
+                        );
+                        continue;
+                    };
+                    let mut found = found.lock().unwrap();
+                    if found.len() < request.count {
+                        on_match(&matched);
+                        found.push(matched);
+                        if found.len() == request.count {
+                            done.store(true, Ordering::Relaxed);
+                            // Let the parked workers see the search is over
+                            limit.wake();
+                        }
+                    }
+                }
+            }
+            attempts.fetch_add(unreported, Ordering::Relaxed);
+        });
     });
 
-    match timeout_fut.await {
-        Ok(result) => result,
-        Err(_) => {
+    let found = found.into_inner().unwrap();
+    if found.len() == request.count {
+        Ok(found)
+    } else {
+        Err("Operation was canceled".to_string())
+    }
+}
+
+/// Runs [`find_vanity_address`] off the async runtime and gives up after
+/// `timeout_secs` or once `cancel_rx` observes `true`. Unlike
+/// `find_vanity_address`, the result keeps the seed phrase of a mnemonic
+/// search.
+pub async fn generate_with_timeout(
+    req: VanityRequest,
+    timeout_secs: u64,
+    mut cancel_rx: watch::Receiver<bool>,
+) -> Result<VanityResult, String> {
+    let matcher = Matcher::new(&req)?;
+
+    debug!("Starting vanity address generation for {}", req);
+
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    let worker_flag = cancel_flag.clone();
+    let mut search = tokio::task::spawn_blocking(move || find_first(&req, &worker_flag));
+
+    let outcome = tokio::select! {
+        joined = &mut search => match joined {
+            Ok(result) => result,
+            Err(_) => Err("Generation task failed".to_string()),
+        },
+        _ = sleep(Duration::from_secs(timeout_secs)) => {
             debug!("Generation timed out");
             Err("Generation timed out".to_string())
-        },
+        }
+        Ok(_) = cancel_rx.wait_for(|cancelled| *cancelled) => {
+            debug!("Generation cancelled");
+            Err("Generation cancelled".to_string())
+        }
+    };
+
+    // Stop the workers if we bailed out early
+    cancel_flag.store(true, Ordering::Relaxed);
+
+    let found = outcome?;
+    let result = VanityResult::from(&found).with_match(&matcher);
+    info!("Found matching address: {}", result.public_key);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const FORMATS: [KeyFormat; 3] = [KeyFormat::Base58, KeyFormat::JsonArray, KeyFormat::Hex];
+
+    #[test]
+    fn key_formats_round_trip() {
+        let keypair = Keypair::new();
+        let bytes = keypair.to_bytes();
+        for format in FORMATS {
+            let encoded = format.encode(&bytes);
+            assert_eq!(format.decode(&encoded).unwrap(), bytes, "{}", format);
+            // Keypair files usually end in a newline
+            assert_eq!(format.decode(&format!("{}\n", encoded)).unwrap(), bytes);
+
+            assert_eq!(format.as_str().parse::<KeyFormat>().unwrap(), format);
+            let json = serde_json::to_string(&format).unwrap();
+            assert_eq!(json, format!("\"{}\"", format));
+            assert_eq!(serde_json::from_str::<KeyFormat>(&json).unwrap(), format);
+        }
+        assert_eq!("JSON".parse::<KeyFormat>().unwrap(), KeyFormat::JsonArray);
+        assert!("pem".parse::<KeyFormat>().is_err());
+    }
+
+    #[test]
+    fn key_formats_match_what_other_tools_write() {
+        let bytes = [0, 1, 171, 255];
+        assert_eq!(KeyFormat::Base58.encode(&bytes), "1Za6");
+        assert_eq!(KeyFormat::JsonArray.encode(&bytes), "[0,1,171,255]");
+        assert_eq!(KeyFormat::Hex.encode(&bytes), "0001abff");
+        assert_eq!(KeyFormat::Hex.decode("0001ABFF").unwrap(), bytes);
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected() {
+        for text in ["abc", "zz", "+f", "0x01", "é1"] {
+            assert!(KeyFormat::Hex.decode(text).is_err(), "{}", text);
+        }
+        assert!(KeyFormat::Base58.decode("0OIl").is_err());
+        assert!(KeyFormat::JsonArray.decode("[256]").is_err());
+    }
+
+    #[test]
+    fn results_convert_between_every_format() {
+        let keypair = Keypair::new();
+        let result = VanityResult::from(&keypair);
+        assert_eq!(result.format, KeyFormat::Base58);
+        for from in FORMATS {
+            for to in FORMATS {
+                let converted = result
+                    .clone()
+                    .with_format(from)
+                    .and_then(|result| result.with_format(to))
+                    .unwrap();
+                assert_eq!(converted.format, to);
+                assert_eq!(*converted.secret_bytes().unwrap(), keypair.to_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn stats_rate_and_eta_follow_the_attempts() {
+        let stats = SearchStats::new(5_000, Duration::from_secs(2), Some(1_000.0), 3);
+        assert_eq!(stats.attempts, 5_000);
+        assert_eq!(stats.keys_per_second, 2_500.0);
+        assert_eq!(stats.expected_attempts, Some(1_000.0));
+        // 3 matches of 1000 attempts each at 2500 a second
+        assert_eq!(stats.eta_seconds, Some(1.2));
+
+        // Nothing left to find
+        let done = SearchStats::new(5_000, Duration::from_secs(2), Some(1_000.0), 0);
+        assert_eq!(done.eta_seconds, Some(0.0));
+    }
+
+    #[test]
+    fn stats_have_no_eta_without_a_rate_or_odds() {
+        // Just started: no time has passed to measure a rate over
+        let started = SearchStats::new(0, Duration::ZERO, Some(1_000.0), 1);
+        assert_eq!(started.keys_per_second, 0.0);
+        assert_eq!(started.eta_seconds, None);
+        let burst = SearchStats::new(100, Duration::ZERO, Some(1_000.0), 1);
+        assert_eq!(burst.keys_per_second, 0.0);
+        assert_eq!(burst.eta_seconds, None);
+
+        let stalled = SearchStats::new(0, Duration::from_secs(5), Some(1_000.0), 1);
+        assert_eq!(stalled.eta_seconds, None);
+
+        // Globs and regexes have no expected attempts
+        let glob = SearchStats::new(5_000, Duration::from_secs(2), None, 1);
+        assert_eq!(glob.keys_per_second, 2_500.0);
+        assert_eq!((glob.expected_attempts, glob.eta_seconds), (None, None));
+        let json = serde_json::to_value(glob).unwrap();
+        assert!(json.get("expected_attempts").is_none());
+        assert!(json.get("eta_seconds").is_none());
+    }
+
+    #[test]
+    fn searches_collect_exactly_count_distinct_matches() {
+        let request = VanityRequest::new("a", VanityPosition::Prefix).with_count(25);
+        let matcher = Matcher::new(&request).unwrap();
+        let reported = AtomicUsize::new(0);
+        let found = find_vanity_addresses(
+            &request,
+            &AtomicBool::new(false),
+            &AtomicU64::new(0),
+            |_| {
+                reported.fetch_add(1, Ordering::Relaxed);
+            },
+        )
+        .unwrap();
+
+        assert_eq!(found.len(), 25);
+        assert_eq!(reported.load(Ordering::Relaxed), 25);
+        let addresses: HashSet<String> = found.iter().map(|m| m.address.to_string()).collect();
+        assert_eq!(addresses.len(), 25);
+        assert!(addresses.iter().all(|address| matcher.is_match(address)));
+    }
+
+    #[test]
+    fn resumed_counter_searches_skip_recorded_matches() {
+        let program = solana_sdk::system_program::id().to_string();
+        let request = VanityRequest::new("a", VanityPosition::Prefix)
+            .with_count(2)
+            .with_pda(PdaOptions::new(&program));
+        let first = find_first(&request, &AtomicBool::new(false)).unwrap();
+        let recorded = vec![VanityResult::from(&first)];
+
+        let rest = request.resume_after(&recorded);
+        assert_eq!(rest.count, 1);
+        let next = find_first(&rest, &AtomicBool::new(false)).unwrap();
+        assert_ne!(next.address, first.address);
+        assert!(next.program_address.unwrap().counter > first.program_address.unwrap().counter);
+    }
+
+    #[test]
+    fn resumed_derived_accounts_start_past_the_highest_seed() {
+        let base = Pubkey::new_unique().to_string();
+        let stake = "Stake11111111111111111111111111111111111111";
+        let options = DerivedAccountOptions::new(base, stake).with_seed_prefix("s-");
+        let request = VanityRequest::new("a", VanityPosition::Prefix)
+            .with_count(3)
+            .with_derived_account(options);
+        let found: Vec<VanityResult> = ["s-z", "s-21"]
+            .iter()
+            .map(|seed| VanityResult {
+                seed: Some(seed.to_string()),
+                ..VanityResult::from(&Keypair::new())
+            })
+            .collect();
+
+        let rest = request.resume_after(&found);
+        assert_eq!(rest.count, 1);
+        assert_eq!(rest.derived_account.unwrap().start_counter, 59);
+    }
+
+    #[test]
+    fn worker_limits_stay_between_one_and_their_capacity() {
+        let limit = WorkerLimit::with_capacity(0, 4);
+        assert_eq!((limit.get(), limit.capacity()), (1, 4));
+        limit.set(9);
+        assert_eq!(limit.get(), 4);
+        limit.set(0);
+        assert_eq!(limit.get(), 1);
+        assert_eq!(WorkerLimit::new(3).capacity(), 3);
+    }
+
+    #[test]
+    fn raising_the_limit_wakes_parked_workers() {
+        let limit = Arc::new(WorkerLimit::with_capacity(1, 2));
+        let parked = limit.clone();
+        let started = std::time::Instant::now();
+        let worker = std::thread::spawn(move || {
+            while 1 >= parked.get() {
+                parked.pause(1);
+            }
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        limit.set(2);
+        worker.join().unwrap();
+        // Sooner than the timeout would have woken it
+        assert!(started.elapsed() < PAUSED_WORKER_POLL);
+    }
+
+    #[test]
+    fn searches_finish_with_workers_parked() {
+        let request = VanityRequest::new("a", VanityPosition::Prefix).with_count(2);
+        let limit = WorkerLimit::with_capacity(1, 3);
+        let found = find_vanity_addresses_limited(
+            &request,
+            &AtomicBool::new(false),
+            &AtomicU64::new(0),
+            &limit,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(found.len(), 2);
     }
 }
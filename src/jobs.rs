@@ -0,0 +1,318 @@
+//! Persistence for server jobs.
+//!
+//! The server keeps live jobs in memory and writes a [`JobRecord`] through a
+//! [`JobStore`] whenever one changes, so a restart can pick interrupted
+//! searches back up. [`SledJobStore`] keeps records in an embedded database
+//! on disk and seals their results and any seed phrase passphrase to its
+//! own age key first, so a copy of the database alone gives away no
+//! secrets.
+
+use crate::encrypt::{open, parse_identities, seal};
+use crate::generator::{VanityRequest, VanityResult};
+use crate::secret::SecretString;
+use age::x25519::Identity;
+use log::error;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// Everything needed to report on a job, or resume it, after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub request: VanityRequest,
+    /// age recipient the client asked results to be sealed to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    /// Queued jobs with a higher priority start first.
+    #[serde(default)]
+    pub priority: i32,
+    /// "pending", "running", "complete" or "error".
+    pub status: String,
+    pub attempts: u64,
+    /// Seconds spent searching, not counting time the server was down.
+    pub elapsed_secs: f64,
+    /// Unix time the job was submitted.
+    pub created_at: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<VanityResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether the client has fetched the finished results.
+    #[serde(default)]
+    pub retrieved: bool,
+}
+
+impl JobRecord {
+    /// Whether the search was still going when the record was written.
+    pub fn is_unfinished(&self) -> bool {
+        self.status == "pending" || self.status == "running"
+    }
+}
+
+/// Where job records are kept between restarts.
+pub trait JobStore: Send + Sync {
+    /// Inserts or replaces the record of job `id`.
+    fn save(&self, id: &str, record: &JobRecord) -> Result<(), String>;
+    fn remove(&self, id: &str) -> Result<(), String>;
+    /// Every stored job, for reloading on startup. Records that can't be
+    /// read are left out or reported as failed rather than failing the load.
+    fn load(&self) -> Result<Vec<(String, JobRecord)>, String>;
+    /// Makes every save and removal so far durable, e.g. on shutdown.
+    fn flush(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Keeps records in memory only; nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryJobStore {
+    records: Mutex<HashMap<String, JobRecord>>,
+}
+
+impl MemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl JobStore for MemoryJobStore {
+    fn save(&self, id: &str, record: &JobRecord) -> Result<(), String> {
+        let mut records = self.records.lock().unwrap();
+        records.insert(id.to_string(), record.clone());
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), String> {
+        self.records.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Vec<(String, JobRecord)>, String> {
+        let records = self.records.lock().unwrap();
+        Ok(records
+            .iter()
+            .map(|(id, record)| (id.clone(), record.clone()))
+            .collect())
+    }
+}
+
+/// A record as written to disk, with its results and passphrase sealed.
+#[derive(Serialize, Deserialize)]
+struct StoredJob {
+    record: JobRecord,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed_results: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sealed_passphrase: Option<String>,
+}
+
+/// Keeps records in a sled database on disk. Writes don't wait for the
+/// disk: sled flushes them in the background about every half second, and
+/// [`JobStore::flush`] forces them out.
+pub struct SledJobStore {
+    db: sled::Db,
+    identity: Identity,
+}
+
+impl SledJobStore {
+    /// Opens or creates a store in `dir`. The age identity that seals
+    /// results lives next to the database in `identity.txt`, readable only
+    /// by the current user; keep it out of backups of the database.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let db = sled::open(dir.join("jobs"))
+            .map_err(|e| format!("Couldn't open the job store in {}: {}", dir.display(), e))?;
+        let identity = load_or_create_identity(&dir.join("identity.txt"))?;
+        Ok(Self { db, identity })
+    }
+
+    // Puts the sealed results and passphrase back into `record`
+    fn unseal(
+        &self,
+        record: &mut JobRecord,
+        results: Option<String>,
+        passphrase: Option<String>,
+    ) -> Result<(), String> {
+        let identities = std::slice::from_ref(&self.identity);
+        if let Some(sealed) = results {
+            let results = open(identities, &sealed)?;
+            record.results = serde_json::from_slice(&results).map_err(|e| e.to_string())?;
+        }
+        if let (Some(sealed), Some(mnemonic)) = (passphrase, &mut record.request.mnemonic) {
+            let passphrase = open(identities, &sealed)?;
+            let passphrase = std::str::from_utf8(&passphrase).map_err(|e| e.to_string())?;
+            mnemonic.passphrase = SecretString::new(passphrase.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn load_or_create_identity(path: &Path) -> Result<Identity, String> {
+    if path.exists() {
+        let contents = Zeroizing::new(
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?,
+        );
+        return parse_identities(&contents).map(|mut identities| identities.remove(0));
+    }
+
+    let identity = Identity::generate();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| {
+            writeln!(file, "# public key: {}", identity.to_public())?;
+            writeln!(file, "{}", identity.to_string().expose_secret())
+        })
+        .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+    Ok(identity)
+}
+
+impl JobStore for SledJobStore {
+    fn save(&self, id: &str, record: &JobRecord) -> Result<(), String> {
+        let mut stored = StoredJob {
+            record: record.clone(),
+            sealed_results: None,
+            sealed_passphrase: None,
+        };
+        let recipient = self.identity.to_public();
+        if !record.results.is_empty() {
+            let results = Zeroizing::new(serde_json::to_vec(&record.results).unwrap());
+            stored.sealed_results = Some(seal(&recipient, &results)?);
+            stored.record.results.clear();
+        }
+        if let Some(mnemonic) = &mut stored.record.request.mnemonic {
+            if !mnemonic.passphrase.is_empty() {
+                let passphrase = mnemonic.passphrase.expose().as_bytes();
+                stored.sealed_passphrase = Some(seal(&recipient, passphrase)?);
+                mnemonic.passphrase = SecretString::default();
+            }
+        }
+
+        let value = serde_json::to_vec(&stored).unwrap();
+        self.db
+            .insert(id, value)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), String> {
+        self.db.remove(id).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn flush(&self) -> Result<(), String> {
+        self.db.flush().map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    // A record that can't be parsed is skipped, and one whose results can't
+    // be unsealed (e.g. after `identity.txt` was lost) comes back as failed,
+    // so one bad record doesn't keep the server from starting
+    fn load(&self) -> Result<Vec<(String, JobRecord)>, String> {
+        let mut records = Vec::new();
+        for entry in self.db.iter() {
+            let (id, value) = entry.map_err(|e| e.to_string())?;
+            let id = String::from_utf8_lossy(&id).into_owned();
+            let stored: StoredJob = match serde_json::from_slice(&value) {
+                Ok(stored) => stored,
+                Err(e) => {
+                    error!("Skipping job {}, which is unreadable: {}", id, e);
+                    continue;
+                }
+            };
+            let StoredJob {
+                mut record,
+                sealed_results,
+                sealed_passphrase,
+            } = stored;
+            if let Err(e) = self.unseal(&mut record, sealed_results, sealed_passphrase) {
+                error!("Couldn't read the secrets of job {}: {}", id, e);
+                record.results.clear();
+                record.status = "error".to_string();
+                record.error = Some("The stored results could not be read".to_string());
+            }
+            records.push((id, record));
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::VanityPosition;
+    use crate::keygen::MnemonicOptions;
+    use solana_sdk::signature::Keypair;
+
+    fn record(results: Vec<VanityResult>) -> JobRecord {
+        JobRecord {
+            request: VanityRequest::new("abc", VanityPosition::Prefix),
+            recipient: None,
+            priority: 0,
+            status: "complete".to_string(),
+            attempts: 1,
+            elapsed_secs: 0.0,
+            created_at: 0,
+            results,
+            error: None,
+            retrieved: false,
+        }
+    }
+
+    #[test]
+    fn unreadable_records_dont_fail_the_load() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        {
+            let store = SledJobStore::open(&dir).unwrap();
+            let result = VanityResult::from(&Keypair::new());
+            store.save("sealed", &record(vec![result])).unwrap();
+            store.save("plain", &record(Vec::new())).unwrap();
+            store.db.insert("garbage", &b"not json"[..]).unwrap();
+            store.db.flush().unwrap();
+        }
+        // A new identity can't open the results sealed to the old one
+        std::fs::remove_file(dir.join("identity.txt")).unwrap();
+
+        let store = SledJobStore::open(&dir).unwrap();
+        let mut records = store.load().unwrap();
+        records.sort_by(|a, b| a.0.cmp(&b.0));
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let ids: Vec<&str> = records.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["plain", "sealed"]);
+        assert_eq!(records[0].1.status, "complete");
+        assert_eq!(records[1].1.status, "error");
+        assert!(records[1].1.results.is_empty());
+    }
+
+    #[test]
+    fn passphrases_are_sealed_on_disk() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let store = SledJobStore::open(&dir).unwrap();
+        let mut job = record(Vec::new());
+        job.request = job
+            .request
+            .with_mnemonic(MnemonicOptions::new(12).with_passphrase("correct horse"));
+        store.save("job", &job).unwrap();
+
+        let raw = store.db.get("job").unwrap().unwrap();
+        let loaded = store.load().unwrap();
+        drop(store);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!String::from_utf8_lossy(&raw).contains("correct horse"));
+        let mnemonic = loaded[0].1.request.mnemonic.as_ref().unwrap();
+        assert_eq!(mnemonic.passphrase.expose(), "correct horse");
+    }
+}
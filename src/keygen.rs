@@ -0,0 +1,472 @@
+//! Candidate keypairs for the search loop, derived without building a
+//! `Keypair` for every attempt.
+//!
+//! Searches can instead draw BIP39 seed phrases, so the result can be
+//! restored in a wallet. Those cost a 2048-round PBKDF2 per attempt and are
+//! far slower.
+//!
+//! Each worker draws standard 32-byte ed25519 seeds from its own ChaCha20
+//! stream, seeded once from the operating system, instead of asking the OS
+//! for fresh randomness on every attempt. Only the public key is computed
+//! per seed; a full `Keypair` is built for the rare seeds that match.
+//!
+//! Seeds are drawn in batches. Each seed still costs one SHA-512 and one
+//! fixed-base scalar multiplication, but the batch's points are brought back
+//! to affine coordinates with a single field inversion instead of one per
+//! key (see the `curve` module).
+
+use crate::curve;
+use crate::derived::DerivedAccountStream;
+use crate::generator::{VanityMatch, VanityRequest};
+use crate::pda::{PdaCandidate, PdaStream};
+use crate::secret::SecretString;
+use crate::split::SplitKeyStream;
+use crate::token::TokenAccountStream;
+use bip39::{Language, Mnemonic, Seed};
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::scalar::Scalar;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use solana_sdk::derivation_path::DerivationPath;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::signer::keypair::{keypair_from_seed, keypair_from_seed_and_derivation_path};
+use std::fmt;
+use std::str::FromStr;
+use zeroize::Zeroize;
+
+/// Seeds drawn per refill of a [`SeedStream`]'s buffer.
+const SEED_BATCH: usize = 64;
+
+/// Generate keys from BIP39 seed phrases that wallets can restore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MnemonicOptions {
+    /// Phrase length: 12 or 24 words.
+    #[serde(default = "default_words")]
+    pub words: usize,
+    /// Optional BIP39 passphrase (the "25th word").
+    #[serde(default, skip_serializing_if = "SecretString::is_empty")]
+    pub passphrase: SecretString,
+    #[serde(default)]
+    pub derivation: Derivation,
+}
+
+fn default_words() -> usize {
+    12
+}
+
+/// How the keypair is derived from the phrase's 64-byte BIP39 seed.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Derivation {
+    /// `m/44'/501'/0'/0'`, the first account in Phantom and Solflare.
+    #[default]
+    Bip44,
+    /// The first 32 bytes of the seed, as `solana-keygen recover` uses
+    /// without a derivation path.
+    Bare,
+}
+
+/// Per-thread source of candidate keys for a search.
+pub enum KeyStream {
+    Seeds(SeedStream),
+    Mnemonics(MnemonicStream),
+    ProgramAddresses(PdaStream),
+    DerivedAccounts(DerivedAccountStream),
+    SplitKeys(SplitKeyStream),
+    /// Wallets from the inner stream, matched by their token account.
+    TokenAccounts(Box<KeyStream>, TokenAccountStream),
+}
+
+/// What a candidate key came from, kept until it turns out to match.
+pub enum Secret {
+    Seed([u8; 32]),
+    // Boxed so bare seeds, returned on every attempt, stay small
+    Mnemonic(Box<(Mnemonic, Keypair)>),
+    ProgramAddress(PdaCandidate),
+    DerivedSeed(u64),
+    SplitKeyStep(u64),
+}
+
+/// Per-thread source of random ed25519 seeds and their public keys.
+pub struct SeedStream {
+    rng: ChaCha20Rng,
+    seeds: Box<[[u8; 32]; SEED_BATCH]>,
+    keys: Box<[[u8; 32]; SEED_BATCH]>,
+    next: usize,
+}
+
+impl SeedStream {
+    /// Starts a stream seeded from operating system randomness.
+    pub fn new() -> Self {
+        Self {
+            rng: ChaCha20Rng::from_entropy(),
+            seeds: Box::new([[0; 32]; SEED_BATCH]),
+            keys: Box::new([[0; 32]; SEED_BATCH]),
+            next: SEED_BATCH,
+        }
+    }
+
+    /// Returns a fresh seed together with its public key bytes.
+    pub fn next_candidate(&mut self) -> ([u8; 32], [u8; 32]) {
+        if self.next == SEED_BATCH {
+            self.rng.fill_bytes(self.seeds.as_flattened_mut());
+            *self.keys = curve::public_keys(&self.seeds);
+            self.next = 0;
+        }
+        let seed = self.seeds[self.next];
+        let key = self.keys[self.next];
+        self.next += 1;
+        (seed, key)
+    }
+}
+
+impl Drop for SeedStream {
+    fn drop(&mut self) {
+        // The unused rest of the batch would otherwise linger on the heap
+        self.seeds.as_flattened_mut().zeroize();
+    }
+}
+
+impl Default for SeedStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MnemonicOptions {
+    pub fn new(words: usize) -> Self {
+        Self {
+            words,
+            passphrase: SecretString::default(),
+            derivation: Derivation::Bip44,
+        }
+    }
+
+    /// Protects the phrase with a BIP39 passphrase.
+    pub fn with_passphrase(mut self, passphrase: impl Into<String>) -> Self {
+        self.passphrase = SecretString::new(passphrase.into());
+        self
+    }
+
+    pub fn with_derivation(mut self, derivation: Derivation) -> Self {
+        self.derivation = derivation;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.words {
+            12 | 24 => Ok(()),
+            _ => Err("Mnemonics must have 12 or 24 words".to_string()),
+        }
+    }
+
+    /// The keypair a wallet restores from `mnemonic` with these options.
+    pub fn keypair(&self, mnemonic: &Mnemonic) -> Keypair {
+        let seed = Seed::new(mnemonic, self.passphrase.expose());
+        match self.derivation {
+            Derivation::Bip44 => keypair_from_seed_and_derivation_path(
+                seed.as_bytes(),
+                Some(DerivationPath::new_bip44(Some(0), Some(0))),
+            ),
+            Derivation::Bare => keypair_from_seed(seed.as_bytes()),
+        }
+        .expect("a 64-byte BIP39 seed always derives a keypair")
+    }
+}
+
+impl Derivation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Derivation::Bip44 => "bip44",
+            Derivation::Bare => "bare",
+        }
+    }
+}
+
+impl fmt::Display for Derivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Derivation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bip44" => Ok(Derivation::Bip44),
+            "bare" => Ok(Derivation::Bare),
+            _ => Err("Derivation must be 'bip44' or 'bare'".to_string()),
+        }
+    }
+}
+
+impl KeyStream {
+    /// Starts worker `worker` of `workers` on the kind of key `request`
+    /// searches for: a program address, a derived account, a split-key
+    /// offset, a seed phrase or a bare seed, possibly standing in for its
+    /// token account. The request must be valid.
+    pub fn new(request: &VanityRequest, worker: usize, workers: usize) -> Self {
+        if let Some(options) = &request.token_account {
+            let wallets = VanityRequest {
+                token_account: None,
+                ..request.clone()
+            };
+            KeyStream::TokenAccounts(
+                Box::new(KeyStream::new(&wallets, worker, workers)),
+                TokenAccountStream::new(options),
+            )
+        } else if let Some(options) = &request.pda {
+            KeyStream::ProgramAddresses(PdaStream::new(options, worker, workers))
+        } else if let Some(options) = &request.derived_account {
+            KeyStream::DerivedAccounts(DerivedAccountStream::new(options, worker, workers))
+        } else if let Some(options) = &request.split_key {
+            KeyStream::SplitKeys(SplitKeyStream::new(options))
+        } else if let Some(options) = &request.mnemonic {
+            KeyStream::Mnemonics(MnemonicStream::new(options.clone()))
+        } else {
+            KeyStream::Seeds(SeedStream::new())
+        }
+    }
+
+    /// Returns a fresh candidate's public key bytes and what it came from.
+    pub fn next_candidate(&mut self) -> ([u8; 32], Secret) {
+        match self {
+            KeyStream::Seeds(seeds) => {
+                let (seed, pubkey) = seeds.next_candidate();
+                (pubkey, Secret::Seed(seed))
+            }
+            KeyStream::Mnemonics(mnemonics) => {
+                let (mnemonic, keypair) = mnemonics.next_candidate();
+                let pubkey = keypair.pubkey().to_bytes();
+                (pubkey, Secret::Mnemonic(Box::new((mnemonic, keypair))))
+            }
+            KeyStream::ProgramAddresses(addresses) => {
+                let (address, candidate) = addresses.next_candidate();
+                (address, Secret::ProgramAddress(candidate))
+            }
+            KeyStream::DerivedAccounts(accounts) => {
+                let (address, counter) = accounts.next_candidate();
+                (address, Secret::DerivedSeed(counter))
+            }
+            KeyStream::SplitKeys(points) => {
+                let (address, steps) = points.next_candidate();
+                (address, Secret::SplitKeyStep(steps))
+            }
+            KeyStream::TokenAccounts(wallets, accounts) => {
+                let (wallet, secret) = wallets.next_candidate();
+                (accounts.token_account(wallet), secret)
+            }
+        }
+    }
+
+    /// Builds the full result for a candidate of this stream that matched
+    /// at `address`, or `None` if the rebuilt keypair doesn't control
+    /// `address`. That would mean the batched key arithmetic in
+    /// [`SeedStream`] went wrong, so the match must not be reported.
+    pub fn finish(&self, address: [u8; 32], secret: Secret) -> Option<VanityMatch> {
+        let found = self.build(address, secret);
+        let Some(keypair) = &found.keypair else {
+            return Some(found);
+        };
+        let wallet = keypair.pubkey().to_bytes();
+        let controlled = match self {
+            KeyStream::TokenAccounts(_, accounts) => accounts.token_account(wallet),
+            _ => wallet,
+        };
+        (controlled == address).then_some(found)
+    }
+
+    // The result for a matching candidate, trusting `address`
+    fn build(&self, address: [u8; 32], secret: Secret) -> VanityMatch {
+        if let KeyStream::TokenAccounts(wallets, _) = self {
+            let mut found = wallets.build(address, secret);
+            found.owner = found.keypair.as_ref().map(Keypair::pubkey);
+            return found;
+        }
+
+        let mut found = VanityMatch {
+            address: Pubkey::new_from_array(address),
+            keypair: None,
+            mnemonic: None,
+            program_address: None,
+            derived_seed: None,
+            owner: None,
+            offset: None,
+        };
+        match (self, secret) {
+            (_, Secret::Seed(seed)) => found.keypair = Some(keypair(&seed)),
+            (_, Secret::Mnemonic(candidate)) => {
+                let (mnemonic, keypair) = *candidate;
+                found.keypair = Some(keypair);
+                found.mnemonic = Some(mnemonic.into_phrase().into());
+            }
+            (KeyStream::ProgramAddresses(addresses), Secret::ProgramAddress(candidate)) => {
+                found.program_address = Some(addresses.program_address(candidate));
+            }
+            (KeyStream::DerivedAccounts(accounts), Secret::DerivedSeed(counter)) => {
+                found.derived_seed = Some(accounts.seed(counter));
+            }
+            (KeyStream::SplitKeys(points), Secret::SplitKeyStep(steps)) => {
+                found.offset = Some(points.offset(steps));
+            }
+            (_, Secret::ProgramAddress(_) | Secret::DerivedSeed(_) | Secret::SplitKeyStep(_)) => {
+                unreachable!("only the matching stream yields these candidates")
+            }
+        }
+        found
+    }
+}
+
+/// Per-thread source of random English BIP39 phrases and their keypairs.
+pub struct MnemonicStream {
+    rng: ChaCha20Rng,
+    options: MnemonicOptions,
+}
+
+impl MnemonicStream {
+    /// Starts a stream seeded from operating system randomness.
+    pub fn new(options: MnemonicOptions) -> Self {
+        Self {
+            rng: ChaCha20Rng::from_entropy(),
+            options,
+        }
+    }
+
+    /// Returns a fresh phrase together with the keypair it restores to.
+    pub fn next_candidate(&mut self) -> (Mnemonic, Keypair) {
+        // 12 words carry 128 bits of entropy and 24 words 256
+        let mut entropy = [0u8; 32];
+        let entropy = &mut entropy[..self.options.words * 4 / 3];
+        self.rng.fill_bytes(entropy);
+        let mnemonic = Mnemonic::from_entropy(entropy, Language::English)
+            .expect("16 or 32 bytes is valid BIP39 entropy");
+        let keypair = self.options.keypair(&mnemonic);
+        (mnemonic, keypair)
+    }
+}
+
+/// Public key bytes of the ed25519 keypair with `seed` as its secret key,
+/// the same key `Keypair::from_bytes` would pair with it. [`SeedStream`]
+/// computes the same keys a batch at a time.
+pub fn public_key(seed: &[u8; 32]) -> [u8; 32] {
+    let hash = Sha512::digest(seed);
+    let mut bits = [0u8; 32];
+    bits.copy_from_slice(&hash[..32]);
+
+    // Clamp as RFC 8032 does
+    bits[0] &= 248;
+    bits[31] &= 127;
+    bits[31] |= 64;
+
+    let point = &Scalar::from_bits(bits) * &ED25519_BASEPOINT_TABLE;
+    point.compress().to_bytes()
+}
+
+/// Builds the full keypair for a seed from a [`SeedStream`].
+pub fn keypair(seed: &[u8; 32]) -> Keypair {
+    keypair_from_seed(seed).expect("a 32-byte seed is always a valid secret key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{find_vanity_addresses, VanityPosition, VanityResult};
+    use solana_sdk::signer::keypair::keypair_from_seed_phrase_and_passphrase;
+    use std::sync::atomic::{AtomicBool, AtomicU64};
+
+    #[test]
+    fn matches_are_only_reported_for_the_keypair_that_controls_them() {
+        let request = VanityRequest::new("abc", VanityPosition::Prefix);
+        let mut keys = KeyStream::new(&request, 0, 1);
+        let (address, secret) = keys.next_candidate();
+        let Secret::Seed(seed) = secret else {
+            panic!("plain searches draw bare seeds");
+        };
+
+        let found = keys.finish(address, Secret::Seed(seed)).unwrap();
+        assert_eq!(found.address.to_bytes(), address);
+        assert_eq!(found.keypair.unwrap().pubkey().to_bytes(), address);
+
+        let mut wrong = address;
+        wrong[0] ^= 1;
+        assert!(keys.finish(wrong, Secret::Seed(seed)).is_none());
+    }
+
+    // The phrase from the web3.js seed phrase example
+    const PHRASE: &str =
+        "pill tomorrow foster begin walnut borrow virtual kick shift mutual shoe scatter";
+
+    fn restored(options: &MnemonicOptions, phrase: &str) -> String {
+        let mnemonic = Mnemonic::from_phrase(phrase, Language::English).unwrap();
+        options.keypair(&mnemonic).pubkey().to_string()
+    }
+
+    #[test]
+    fn phrases_restore_the_wallet_addresses() {
+        let cases = [
+            (
+                "",
+                Derivation::Bare,
+                "5ZWj7a1f8tWkjBESHKgrLmXshuXxqeY9SYcfbshpAqPG",
+            ),
+            (
+                "",
+                Derivation::Bip44,
+                "5F86TNSTre3CYwZd1wELsGQGhqG2HkN3d8zxhbyBSnzm",
+            ),
+            (
+                "vanity",
+                Derivation::Bare,
+                "9MTFWeKCgwucP9Yn6997AhSdCHPN4bL1Ev4AiCnm5wB8",
+            ),
+            (
+                "vanity",
+                Derivation::Bip44,
+                "9TLhKrebZYnd7DXBrWr6N8je47XAZwsKrmeSDKhEnFGm",
+            ),
+        ];
+        for (passphrase, derivation, address) in cases {
+            let options = MnemonicOptions::new(12)
+                .with_passphrase(passphrase)
+                .with_derivation(derivation);
+            assert_eq!(
+                restored(&options, PHRASE),
+                address,
+                "{} {:?}",
+                derivation,
+                passphrase
+            );
+        }
+    }
+
+    #[test]
+    fn found_phrases_restore_the_found_address() {
+        for derivation in [Derivation::Bip44, Derivation::Bare] {
+            let options = MnemonicOptions::new(12)
+                .with_passphrase("vanity")
+                .with_derivation(derivation);
+            let request =
+                VanityRequest::new("a", VanityPosition::Prefix).with_mnemonic(options.clone());
+            let found = find_vanity_addresses(
+                &request,
+                &AtomicBool::new(false),
+                &AtomicU64::new(0),
+                |_| {},
+            )
+            .unwrap();
+            let result = VanityResult::from(&found[0]);
+            let phrase = result.mnemonic.as_ref().unwrap().expose();
+
+            assert_eq!(restored(&options, phrase), result.public_key);
+            if derivation == Derivation::Bare {
+                // As `solana-keygen recover` reads it
+                let keypair = keypair_from_seed_phrase_and_passphrase(phrase, "vanity").unwrap();
+                assert_eq!(keypair.pubkey().to_string(), result.public_key);
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Solana vanity address search engine.
+//!
+//! This crate holds the grinding logic shared by the `solana-vanity-generator`
+//! API server, the `vanity` CLI and the `vanity-tui` terminal interface. It can
+//! also be embedded directly in other Rust services:
+//!
+//! ```no_run
+//! use std::sync::atomic::AtomicBool;
+//! use solana_vanity_generator::{find_vanity_address, VanityPosition, VanityRequest};
+//!
+//! let request = VanityRequest::new("abc", VanityPosition::Prefix);
+//! let cancel_flag = AtomicBool::new(false);
+//! let keypair = find_vanity_address(&request, &cancel_flag).unwrap();
+//! ```
+
+pub mod config;
+mod curve;
+pub mod derived;
+pub mod encrypt;
+pub mod estimate;
+pub mod events;
+pub mod generator;
+pub mod jobs;
+pub mod keygen;
+pub mod matcher;
+pub mod pda;
+mod prefilter;
+pub mod scheduler;
+pub mod secret;
+pub mod split;
+pub mod token;
+
+pub use config::{ConfigLayer, ServerConfig};
+pub use derived::{DerivedAccountOptions, MAX_SEED_PREFIX_LEN};
+pub use encrypt::{parse_identities, parse_recipient};
+pub use estimate::{measure_keys_per_second, prefix_probability, Estimate};
+pub use events::{ApiError, EventParser, JobEvent, JobEventKind, JobStatus};
+pub use generator::{
+    find_vanity_address, find_vanity_addresses, find_vanity_addresses_limited,
+    generate_with_timeout, KeyFormat, SearchStats, VanityMatch, VanityPosition, VanityRequest,
+    VanityResult, WorkerLimit, MAX_COUNT, MAX_PATTERN_LEN, MIN_PATTERN_LEN,
+};
+pub use jobs::{JobRecord, JobStore, MemoryJobStore, SledJobStore};
+pub use keygen::{Derivation, MnemonicOptions};
+pub use matcher::{check_patterns, MatchMode, Matcher, PatternError};
+pub use pda::{PdaOptions, ProgramAddress, VariableSeed};
+pub use scheduler::{QueuedJob, Scheduler};
+pub use secret::SecretString;
+pub use split::{CombinedKey, SplitKey, SplitKeyOptions};
+pub use token::{TokenAccountOptions, TokenProgram};
@@ -1,46 +1,100 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{web, App as WebApp, HttpResponse, HttpServer, Responder};
+use age::x25519::Recipient;
 use chrono::Utc;
+use clap::{App, Arg, ArgMatches};
 use dashmap::DashMap;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use solana_sdk::signature::{Keypair, Signer};
-use std::sync::atomic::{AtomicBool, Ordering};
+use solana_vanity_generator::config::split_origins;
+use solana_vanity_generator::{
+    check_patterns, find_vanity_addresses_limited, measure_keys_per_second, parse_recipient,
+    ConfigLayer, DerivedAccountOptions, Estimate, JobEvent, JobEventKind, JobRecord, JobStore,
+    KeyFormat, MatchMode, Matcher, MnemonicOptions, PdaOptions, QueuedJob, Scheduler, SearchStats,
+    ServerConfig, SledJobStore, SplitKey, SplitKeyOptions, TokenAccountOptions, VanityPosition,
+    VanityRequest, VanityResult, WorkerLimit,
+};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio::time::{sleep, Duration};
 use uuid::Uuid;
 
+// How often an event stream reports progress when nothing else happens
+const EVENT_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
 // Structures for request and response
 #[derive(Debug, Deserialize)]
 struct GenerateRequest {
+    #[serde(default)]
     pattern: String,
-    position: String, // "prefix" or "suffix"
+    #[serde(default)]
+    patterns: Vec<String>, // Alternatives matched in the same pass
+    position: String, // "prefix", "suffix" or "both"
+    #[serde(default)]
+    mode: MatchMode, // "exact", "glob" or "regex"
+    #[serde(default)]
+    suffix: Option<String>, // Required ending when position is "both"
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default = "default_count")]
+    count: usize, // Number of matching addresses to collect
+    #[serde(default)]
+    mnemonic: Option<MnemonicOptions>, // Generate restorable BIP39 seed phrases
+    #[serde(default)]
+    pda: Option<PdaOptions>, // Search program derived addresses instead of keypairs
+    #[serde(default)]
+    derived_account: Option<DerivedAccountOptions>, // Search create_with_seed seeds instead
+    #[serde(default)]
+    token_account: Option<TokenAccountOptions>, // Match the wallet's token account for a mint
+    #[serde(default)]
+    split_key: Option<SplitKeyOptions>, // Search offsets to a client's point; no private key here
+    #[serde(default)]
+    recipient: Option<String>, // age public key ("age1...") to seal results to
+    #[serde(default)]
+    priority: i32, // Queued jobs with a higher priority start first
+}
+
+fn default_count() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusQuery {
+    #[serde(default)]
+    format: Option<String>, // "base58" (default), "json-array" or "hex"
 }
 
 #[derive(Debug, Serialize)]
 struct GenerateResponse {
     job_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected_attempts: Option<f64>,
 }
 
 #[derive(Debug, Serialize)]
 struct StatusResponse {
-    status: String, // "pending", "running", "complete", "error"
+    status: String, // "pending" (queued), "running", "complete", "error"
     #[serde(skip_serializing_if = "Option::is_none")]
-    progress: Option<f64>,
+    queue_position: Option<usize>, // Place in the queue while pending, from 1
     #[serde(skip_serializing_if = "Option::is_none")]
-    result: Option<AddressResult>,
+    workers: Option<usize>, // Worker threads the search has while running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<f64>, // Fraction of the requested addresses found so far
+    found: usize,
+    requested: usize,
+    #[serde(flatten)]
+    stats: SearchStats, // Attempts, keys/sec, expected attempts and ETA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<VanityResult>, // First match, once the job is complete
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<VanityResult>>, // Every match so far, for multi-address jobs
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
 }
 
-#[derive(Debug, Serialize, Clone)]
-struct AddressResult {
-    public_key: String,
-    private_key: String,
-}
-
 #[derive(Debug, Serialize)]
 struct HealthResponse {
     status: String,
@@ -50,19 +104,332 @@ struct HealthResponse {
 // Internal job tracking
 struct Job {
     status: String,
-    #[allow(dead_code)]
-    pattern: String,
-    #[allow(dead_code)]
-    position: String,
+    request: VanityRequest,
+    recipient: Option<String>, // Kept so a resumed search seals to the same key
+    priority: i32,             // Higher runs first
+    created_at: i64,           // Unix time, for the store
     start_time: Instant,
+    queued_at: Option<Instant>, // Set while the job waits for a slot
+    end_time: Option<Instant>,
     cancel_flag: Arc<AtomicBool>,
-    result: Option<AddressResult>,
+    attempts: Arc<AtomicU64>,
+    expected_attempts: Option<f64>,
+    results: Vec<VanityResult>,
     error: Option<String>,
+    retrieved_at: Option<Instant>, // First time the finished results were fetched
+    evicted: bool,                 // Dropped by the janitor; nothing more is stored
+    updates: watch::Sender<()>,    // Wakes event streams whenever the job changes
+}
+
+impl Job {
+    // Snapshot for the job store
+    fn record(&self) -> JobRecord {
+        let end = self
+            .end_time
+            .or(self.queued_at)
+            .unwrap_or_else(Instant::now);
+        JobRecord {
+            request: self.request.clone(),
+            recipient: self.recipient.clone(),
+            priority: self.priority,
+            status: self.status.clone(),
+            attempts: self.attempts.load(Ordering::Relaxed),
+            elapsed_secs: end.duration_since(self.start_time).as_secs_f64(),
+            created_at: self.created_at,
+            results: self.results.clone(),
+            error: self.error.clone(),
+            retrieved: self.retrieved_at.is_some(),
+        }
+    }
+
+    // Rebuild a job from the store. Its clock is set back by the time it
+    // already spent, so keys/sec and the ETA carry on where they left off.
+    // Retention restarts from now, since downtime shouldn't count against it.
+    fn from_record(record: JobRecord) -> Self {
+        let now = Instant::now();
+        let elapsed = Duration::from_secs_f64(record.elapsed_secs.max(0.0));
+        let finished = !record.is_unfinished();
+        Job {
+            status: record.status,
+            request: record.request,
+            recipient: record.recipient,
+            priority: record.priority,
+            created_at: record.created_at,
+            start_time: now.checked_sub(elapsed).unwrap_or(now),
+            queued_at: None,
+            end_time: finished.then_some(now),
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            attempts: Arc::new(AtomicU64::new(record.attempts)),
+            expected_attempts: None,
+            results: record.results,
+            error: record.error,
+            retrieved_at: record.retrieved.then_some(now),
+            evicted: false,
+            updates: watch::channel(()).0,
+        }
+    }
 }
 
 // Global state
 struct AppState {
     jobs: DashMap<String, Arc<Mutex<Job>>>,
+    store: Arc<dyn JobStore>, // Where jobs are written through, to survive restarts
+    retention: Retention,     // When the janitor evicts jobs
+    scheduler: std::sync::Mutex<Scheduler<Search>>, // Which jobs run, and on how many cores
+    rates: HashRates,         // Measured at startup, for estimates
+}
+
+// Everything a queued job's search needs
+struct Search {
+    job_ref: Arc<Mutex<Job>>,
+    request: VanityRequest,
+    matcher: Matcher,
+    recipient: Option<Recipient>,
+}
+
+// How long each kind of job is kept
+struct Retention {
+    interval: Duration,  // How often the janitor runs
+    complete: Duration,  // Finished jobs whose results nobody fetched yet
+    retrieved: Duration, // Finished jobs, counted from the first fetch of their results
+    error: Duration,     // Failed jobs, kept longer for debugging
+    running: Duration,   // Longest a search may run before it is cancelled
+}
+
+impl Retention {
+    fn new(config: &ServerConfig) -> Self {
+        Retention {
+            interval: Duration::from_secs(config.cleanup_interval_secs),
+            complete: Duration::from_secs(config.ttl_complete_secs),
+            retrieved: Duration::from_secs(config.ttl_retrieved_secs),
+            error: Duration::from_secs(config.ttl_error_secs),
+            running: Duration::from_secs(config.ttl_running_secs),
+        }
+    }
+
+    fn is_expired(&self, job: &Job, now: Instant) -> bool {
+        match job.end_time {
+            // Only time spent searching counts; a pending job's clock stopped
+            // when it was queued
+            None => {
+                let searched = job.queued_at.unwrap_or(now);
+                searched.saturating_duration_since(job.start_time) > self.running
+            }
+            Some(end) if job.status == "error" => now.duration_since(end) > self.error,
+            Some(end) => match job.retrieved_at {
+                Some(retrieved) => now.duration_since(retrieved) > self.retrieved,
+                None => now.duration_since(end) > self.complete,
+            },
+        }
+    }
+}
+
+// Command-line settings, which override every other source, and the
+// config file they name
+fn config_flags() -> Result<(Option<PathBuf>, ConfigLayer), String> {
+    let setting = |name: &'static str, value: &'static str, help: &'static str| {
+        Arg::with_name(name)
+            .long(name)
+            .value_name(value)
+            .takes_value(true)
+            .help(help)
+    };
+    let matches = App::new("solana-vanity-generator")
+        .about("HTTP API that grinds Solana vanity addresses")
+        .arg(setting(
+            "config",
+            "FILE",
+            "TOML config file [default: vanity.toml, if present]",
+        ))
+        .arg(setting(
+            "host",
+            "HOST",
+            "Address to listen on [default: 127.0.0.1]",
+        ))
+        .arg(setting("port", "PORT", "Port to listen on [default: 3001]"))
+        .arg(setting(
+            "threads",
+            "N",
+            "Search threads shared by the running jobs [default: one per core]",
+        ))
+        .arg(setting(
+            "data-dir",
+            "DIR",
+            "Where the job store lives [default: data]",
+        ))
+        .arg(setting(
+            "max-running-jobs",
+            "N",
+            "Jobs searching at once [default: 4]",
+        ))
+        .arg(setting(
+            "max-queued-jobs",
+            "N",
+            "Jobs waiting before new ones are turned away [default: 100]",
+        ))
+        .arg(setting(
+            "cleanup-interval",
+            "SECS",
+            "How often old jobs are evicted [default: 60]",
+        ))
+        .arg(setting(
+            "ttl-complete",
+            "SECS",
+            "Keep finished jobs whose results weren't fetched [default: 86400]",
+        ))
+        .arg(setting(
+            "ttl-retrieved",
+            "SECS",
+            "Keep finished jobs after their results are fetched [default: 600]",
+        ))
+        .arg(setting(
+            "ttl-error",
+            "SECS",
+            "Keep failed jobs [default: 604800]",
+        ))
+        .arg(setting(
+            "ttl-running",
+            "SECS",
+            "Cancel searches running longer [default: 86400]",
+        ))
+        .arg(setting(
+            "cors-origins",
+            "ORIGINS",
+            "Comma-separated origins browsers may call from [default: any]",
+        ))
+        .get_matches();
+
+    let flags = ConfigLayer {
+        host: matches.value_of("host").map(str::to_string),
+        port: flag_value(&matches, "port")?,
+        threads: flag_value(&matches, "threads")?,
+        data_dir: matches.value_of("data-dir").map(PathBuf::from),
+        max_running_jobs: flag_value(&matches, "max-running-jobs")?,
+        max_queued_jobs: flag_value(&matches, "max-queued-jobs")?,
+        cleanup_interval_secs: flag_value(&matches, "cleanup-interval")?,
+        ttl_complete_secs: flag_value(&matches, "ttl-complete")?,
+        ttl_retrieved_secs: flag_value(&matches, "ttl-retrieved")?,
+        ttl_error_secs: flag_value(&matches, "ttl-error")?,
+        ttl_running_secs: flag_value(&matches, "ttl-running")?,
+        cors_origins: matches.value_of("cors-origins").map(split_origins),
+    };
+    Ok((matches.value_of("config").map(PathBuf::from), flags))
+}
+
+// A flag parsed as `T`, or None if it wasn't given
+fn flag_value<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<Option<T>, String> {
+    matches
+        .value_of(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("--{} has an invalid value '{}'", name, value))
+        })
+        .transpose()
+}
+
+// Candidates per second for each kind of search
+struct HashRates {
+    keys: f64,
+    mnemonics: f64,
+    program_addresses: f64,
+    derived_accounts: f64,
+    token_accounts: f64,
+    split_keys: f64,
+}
+
+impl HashRates {
+    // Grind each kind of candidate briefly, before any jobs compete for the cores
+    fn measure() -> Self {
+        let sample = VanityRequest::new("", VanityPosition::Prefix);
+        let program = solana_sdk::system_program::id().to_string();
+        Self {
+            keys: measure_keys_per_second(&sample, Duration::from_secs(1)),
+            mnemonics: measure_keys_per_second(
+                &sample.clone().with_mnemonic(MnemonicOptions::new(12)),
+                Duration::from_millis(500),
+            ),
+            program_addresses: measure_keys_per_second(
+                &sample.clone().with_pda(PdaOptions::new(&program)),
+                Duration::from_millis(500),
+            ),
+            derived_accounts: measure_keys_per_second(
+                &sample
+                    .clone()
+                    .with_derived_account(DerivedAccountOptions::new(&program, &program)),
+                Duration::from_millis(500),
+            ),
+            token_accounts: measure_keys_per_second(
+                &sample
+                    .clone()
+                    .with_token_account(TokenAccountOptions::new(&program)),
+                Duration::from_millis(500),
+            ),
+            split_keys: measure_keys_per_second(
+                &sample.with_split_key(SplitKeyOptions::new(SplitKey::generate().public_point())),
+                Duration::from_millis(500),
+            ),
+        }
+    }
+
+    fn for_request(&self, request: &VanityRequest) -> f64 {
+        if request.pda.is_some() {
+            self.program_addresses
+        } else if request.derived_account.is_some() {
+            self.derived_accounts
+        } else if request.split_key.is_some() {
+            self.split_keys
+        } else if request.mnemonic.is_some() {
+            self.mnemonics
+        } else if request.token_account.is_some() {
+            self.token_accounts
+        } else {
+            self.keys
+        }
+    }
+}
+
+// Validate the pattern and position and compile them for searching. Errors
+// come back as the body of a 400 response.
+fn parse_request(req: &GenerateRequest) -> Result<(VanityRequest, Matcher), serde_json::Value> {
+    let position = req
+        .position
+        .parse::<VanityPosition>()
+        .map_err(|err| serde_json::json!({ "error": err }))?;
+
+    // Either field may carry the candidates; an empty `pattern` takes the first
+    let mut patterns = req.patterns.clone();
+    let pattern = if req.pattern.is_empty() && !patterns.is_empty() {
+        patterns.remove(0)
+    } else {
+        req.pattern.clone()
+    };
+
+    let mut request = VanityRequest::new(pattern, position)
+        .with_patterns(patterns)
+        .with_mode(req.mode)
+        .with_case_sensitive(req.case_sensitive)
+        .with_count(req.count);
+    request.suffix = req.suffix.clone();
+    request.mnemonic = req.mnemonic.clone();
+    request.pda = req.pda.clone();
+    request.derived_account = req.derived_account.clone();
+    request.token_account = req.token_account.clone();
+    request.split_key = req.split_key.clone();
+
+    // Point at the exact character when a pattern can never match
+    if let Err(err) = check_patterns(&request) {
+        return Err(serde_json::json!({
+            "error": err.to_string(),
+            "pattern": err.pattern,
+            "index": err.index,
+            "character": err.character,
+            "reason": err.reason,
+        }));
+    }
+    let matcher = Matcher::new(&request).map_err(|err| serde_json::json!({ "error": err }))?;
+
+    Ok((request, matcher))
 }
 
 // Generate a vanity address
@@ -70,133 +437,341 @@ async fn generate_address(
     req: web::Json<GenerateRequest>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let pattern = req.pattern.clone();
-    let position = req.position.clone();
+    let (request, matcher) = match parse_request(&req) {
+        Ok(parsed) => parsed,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    let recipient = match req.recipient.as_deref().map(parse_recipient).transpose() {
+        Ok(recipient) => recipient,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
 
-    // Validate the pattern and position
-    if pattern.is_empty() {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Pattern cannot be empty"
-        }));
-    }
+    let expected_attempts = matcher.expected_attempts();
 
-    if position != "prefix" && position != "suffix" {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Position must be 'prefix' or 'suffix'"
+    // Turn the job away rather than let the queue grow without bound. The
+    // lock is held until the job is queued, so concurrent requests can't
+    // both take the last place.
+    let mut scheduler = data.scheduler.lock().unwrap();
+    if scheduler.is_full() {
+        return HttpResponse::ServiceUnavailable().json(serde_json::json!({
+            "error": format!(
+                "The job queue is full ({} jobs waiting); try again later",
+                scheduler.max_queued()
+            )
         }));
     }
 
     // Create a new job
     let job_id = Uuid::new_v4().to_string();
-    let cancel_flag = Arc::new(AtomicBool::new(false));
-
     let job = Job {
         status: "pending".to_string(),
-        pattern: pattern.clone(),
-        position: position.clone(),
+        request: request.clone(),
+        recipient: req.recipient.clone(),
+        priority: req.priority,
+        created_at: Utc::now().timestamp(),
         start_time: Instant::now(),
-        cancel_flag: cancel_flag.clone(),
-        result: None,
+        queued_at: Some(Instant::now()),
+        end_time: None,
+        cancel_flag: Arc::new(AtomicBool::new(false)),
+        attempts: Arc::new(AtomicU64::new(0)),
+        expected_attempts,
+        results: Vec::new(),
         error: None,
+        retrieved_at: None,
+        evicted: false,
+        updates: watch::channel(()).0,
     };
+    persist(data.store.as_ref(), &job_id, &job);
 
     // Store the job
-    data.jobs.insert(job_id.clone(), Arc::new(Mutex::new(job)));
+    let job_ref = Arc::new(Mutex::new(job));
+    data.jobs.insert(job_id.clone(), job_ref.clone());
 
-    // Launch background task to find the address
-    let job_ref = data.jobs.get(&job_id).unwrap().clone();
-    let pattern_clone = pattern.clone();
-    let position_clone = position.clone();
+    // Queue the search; it starts right away if a slot is free
+    scheduler.enqueue(QueuedJob {
+        job_id: job_id.clone(),
+        priority: req.priority,
+        job: Search {
+            job_ref,
+            request,
+            matcher,
+            recipient,
+        },
+    });
+    drop(scheduler);
+    run_queued(&data);
+
+    HttpResponse::Ok().json(GenerateResponse {
+        job_id,
+        expected_attempts,
+    })
+}
+
+// Start queued jobs while there are free slots
+fn run_queued(data: &web::Data<AppState>) {
+    let started = data.scheduler.lock().unwrap().start_next();
+    for (queued, limit) in started {
+        spawn_search(data.clone(), queued, limit);
+    }
+}
 
+// Search for `request.count` more matches in the background, recording each
+// one on the job and in the store as it is found. When it ends, its slot
+// goes to the next queued job.
+fn spawn_search(data: web::Data<AppState>, queued: QueuedJob<Search>, limit: Arc<WorkerLimit>) {
+    let QueuedJob {
+        job_id,
+        job:
+            Search {
+                job_ref,
+                request,
+                matcher,
+                recipient,
+            },
+        ..
+    } = queued;
     tokio::spawn(async move {
-        // Update job status to running
-        {
+        // Update job status to running; time spent waiting isn't search time
+        let (cancel_flag, attempts) = {
             let mut job = job_ref.lock().await;
             job.status = "running".to_string();
-        }
-
-        // Find address in background
-        let cancel_flag_clone = cancel_flag.clone();
-        let pattern_clone2 = pattern_clone.clone();
-        let position_clone2 = position_clone.clone();
+            if let Some(queued_at) = job.queued_at.take() {
+                job.start_time += queued_at.elapsed();
+            }
+            persist(data.store.as_ref(), &job_id, &job);
+            (job.cancel_flag.clone(), job.attempts.clone())
+        };
 
+        // Find addresses in background, recording each one as it is found
+        let worker_job = job_ref.clone();
+        let worker_store = data.store.clone();
+        let worker_id = job_id.clone();
         let result = tokio::task::spawn_blocking(move || {
-            find_vanity_address(&pattern_clone2, &position_clone2, cancel_flag_clone)
+            find_vanity_addresses_limited(&request, &cancel_flag, &attempts, &limit, |keypair| {
+                let mut result = VanityResult::from(keypair).with_match(&matcher);
+                // Seal before storing, so the job never holds a readable key
+                if let Some(recipient) = &recipient {
+                    result = result
+                        .encrypt_to(recipient)
+                        .expect("sealing to a parsed key");
+                }
+                let mut job = worker_job.blocking_lock();
+                job.results.push(result);
+                persist(worker_store.as_ref(), &worker_id, &job);
+            })
         })
         .await;
 
         // Update job with result
-        let mut job = job_ref.lock().await;
-        match result {
-            Ok(Ok(keypair)) => {
-                job.status = "complete".to_string();
-                job.result = Some(AddressResult {
-                    public_key: keypair.pubkey().to_string(),
-                    private_key: bs58::encode(&keypair.to_bytes()).into_string(),
-                });
+        {
+            let mut job = job_ref.lock().await;
+            job.end_time = Some(Instant::now());
+            match result {
+                Ok(Ok(_)) => {
+                    job.status = "complete".to_string();
+                }
+                Ok(Err(err)) => {
+                    job.status = "error".to_string();
+                    job.error = Some(err);
+                }
+                Err(_) => {
+                    job.status = "error".to_string();
+                    job.error = Some("Task was canceled".to_string());
+                }
             }
-            Ok(Err(err)) => {
-                job.status = "error".to_string();
-                job.error = Some(err);
+            persist(data.store.as_ref(), &job_id, &job);
+        }
+
+        data.scheduler.lock().unwrap().finish(&job_id);
+        run_queued(&data);
+    });
+}
+
+// Write a job through to the store and wake its event streams. A failure
+// only costs the job its survival across a restart, so the search carries on.
+fn persist(store: &dyn JobStore, job_id: &str, job: &Job) {
+    job.updates.send_replace(());
+    if job.evicted {
+        return;
+    }
+    if let Err(err) = store.save(job_id, &job.record()) {
+        eprintln!("⚠️  Couldn't save job {}: {}", job_id, err);
+    }
+}
+
+// Reload stored jobs and queue the searches the last run didn't finish, in
+// the order they were submitted. Returns how many were queued.
+fn restore_jobs(data: &web::Data<AppState>) -> Result<usize, String> {
+    let mut records = data.store.load()?;
+    records.sort_by_key(|(_, record)| record.created_at);
+
+    let mut resumed = 0;
+    for (job_id, record) in records {
+        let unfinished = record.is_unfinished();
+        let mut job = Job::from_record(record);
+
+        // Check the stored request again; the server may have changed since
+        let search = Matcher::new(&job.request).and_then(|matcher| {
+            let recipient = job.recipient.as_deref().map(parse_recipient).transpose()?;
+            Ok((matcher, recipient))
+        });
+        if let Ok((matcher, _)) = &search {
+            job.expected_attempts = matcher.expected_attempts();
+        }
+        // Counter searches pick up past their last match, so it isn't found again
+        let rest = job.request.resume_after(&job.results);
+
+        let mut resume = None;
+        if unfinished {
+            match search {
+                Ok((matcher, recipient)) if rest.count > 0 => {
+                    resume = Some((rest, matcher, recipient));
+                    job.status = "pending".to_string();
+                    job.queued_at = Some(Instant::now());
+                }
+                Ok(_) => job.status = "complete".to_string(),
+                Err(err) => {
+                    job.status = "error".to_string();
+                    job.error = Some(format!("Couldn't resume: {}", err));
+                }
             }
-            Err(_) => {
-                job.status = "error".to_string();
-                job.error = Some("Task was canceled".to_string());
+            if resume.is_none() {
+                job.end_time = Some(Instant::now());
+                persist(data.store.as_ref(), &job_id, &job);
             }
         }
-    });
 
-    HttpResponse::Ok().json(GenerateResponse { job_id })
+        let priority = job.priority;
+        let job_ref = Arc::new(Mutex::new(job));
+        data.jobs.insert(job_id.clone(), job_ref.clone());
+        if let Some((request, matcher, recipient)) = resume {
+            data.scheduler.lock().unwrap().enqueue(QueuedJob {
+                job_id,
+                priority,
+                job: Search {
+                    job_ref,
+                    request,
+                    matcher,
+                    recipient,
+                },
+            });
+            resumed += 1;
+        }
+    }
+    Ok(resumed)
 }
 
-// Function to find a vanity address
-fn find_vanity_address(
-    pattern: &str,
-    position: &str,
-    cancel_flag: Arc<AtomicBool>,
-) -> Result<Keypair, String> {
-    let pattern_lower = pattern.to_lowercase();
-
-    // Generate keypairs in parallel
-    let num_cpus = num_cpus::get();
-    let result = (0..num_cpus).into_par_iter().find_map_any(|_| {
-        while !cancel_flag.load(Ordering::Relaxed) {
-            let keypair = Keypair::new();
-            let pubkey_str = keypair.pubkey().to_string();
-
-            let match_found = match position {
-                "prefix" => pubkey_str.to_lowercase().starts_with(&pattern_lower),
-                "suffix" => pubkey_str.to_lowercase().ends_with(&pattern_lower),
-                _ => false,
-            };
-
-            if match_found {
-                return Some(keypair);
+// Periodically record the attempts of running jobs, so a resumed search
+// reports its true total
+async fn save_running_jobs(data: web::Data<AppState>) {
+    loop {
+        sleep(Duration::from_secs(10)).await;
+
+        for (job_id, job_ref) in job_refs(&data) {
+            let job = job_ref.lock().await;
+            if job.status == "running" {
+                persist(data.store.as_ref(), &job_id, &job);
             }
         }
-        None
-    });
+    }
+}
+
+// Every job, copied out so the map isn't held while a job lock is awaited
+fn job_refs(data: &AppState) -> Vec<(String, Arc<Mutex<Job>>)> {
+    data.jobs
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect()
+}
+
+// Estimate how long a search would take on this server
+async fn estimate(req: web::Json<GenerateRequest>, data: web::Data<AppState>) -> impl Responder {
+    match parse_request(&req) {
+        Ok((request, matcher)) => {
+            let keys_per_second = data.rates.for_request(&request);
+            HttpResponse::Ok().json(Estimate::new(&matcher, request.count, keys_per_second))
+        }
+        Err(err) => HttpResponse::BadRequest().json(err),
+    }
+}
 
-    match result {
-        Some(keypair) => Ok(keypair),
-        None => Err("Operation was canceled".to_string()),
+// The key format a status or event request asked for
+fn key_format(query: &StatusQuery) -> Result<KeyFormat, String> {
+    query
+        .format
+        .as_deref()
+        .map_or(Ok(KeyFormat::Base58), str::parse)
+}
+
+// What /status and the event stream report for a job. Handing out the
+// results of a finished job counts as retrieving them.
+fn status_response(
+    data: &AppState,
+    job_id: &str,
+    job: &mut Job,
+    format: KeyFormat,
+) -> StatusResponse {
+    // The short retention for fetched results starts with the first fetch
+    if job.status == "complete" && job.retrieved_at.is_none() {
+        job.retrieved_at = Some(Instant::now());
+        persist(data.store.as_ref(), job_id, job);
+    }
+    // Results are stored base58-encoded, so re-encoding can't fail
+    let results: Vec<VanityResult> = job
+        .results
+        .iter()
+        .map(|result| result.clone().with_format(format).unwrap())
+        .collect();
+    let found = results.len();
+    let requested = job.request.count;
+    let elapsed = job.end_time.unwrap_or_else(Instant::now) - job.start_time;
+    let mut stats = SearchStats::new(
+        job.attempts.load(Ordering::Relaxed),
+        elapsed,
+        job.expected_attempts,
+        requested - found,
+    );
+    if job.end_time.is_some() {
+        stats.eta_seconds = None;
+    }
+    let (queue_position, workers) = {
+        let scheduler = data.scheduler.lock().unwrap();
+        (scheduler.position(job_id), scheduler.workers(job_id))
+    };
+    StatusResponse {
+        status: job.status.clone(),
+        queue_position,
+        workers,
+        progress: Some(found as f64 / requested as f64),
+        found,
+        requested,
+        stats,
+        result: if job.status == "complete" {
+            results.first().cloned()
+        } else {
+            None
+        },
+        results: (requested > 1).then_some(results),
+        error: job.error.clone(),
     }
 }
 
 // Get job status
-async fn get_status(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+async fn get_status(
+    path: web::Path<String>,
+    query: web::Query<StatusQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
     let job_id = path.into_inner();
+    let format = match key_format(&query) {
+        Ok(format) => format,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
 
     match data.jobs.get(&job_id) {
         Some(job_ref) => {
-            let job = job_ref.lock().await;
-            let response = StatusResponse {
-                status: job.status.clone(),
-                progress: None, // We could compute this if we tracked iterations
-                result: job.result.clone(),
-                error: job.error.clone(),
-            };
-            HttpResponse::Ok().json(response)
+            let mut job = job_ref.lock().await;
+            HttpResponse::Ok().json(status_response(&data, &job_id, &mut job, format))
         }
         None => HttpResponse::NotFound().json(serde_json::json!({
             "error": "Job not found"
@@ -204,15 +779,116 @@ async fn get_status(path: web::Path<String>, data: web::Data<AppState>) -> impl
     }
 }
 
+// Where a client's event stream has got to
+struct EventStream {
+    data: web::Data<AppState>,
+    job_id: String,
+    job_ref: Arc<Mutex<Job>>,
+    format: KeyFormat,
+    updates: watch::Receiver<()>,
+    status: Option<String>, // Last status sent
+    sent: usize,            // Results sent so far
+    done: bool,
+}
+
+impl EventStream {
+    // The events since the last call, waiting for a change or the next
+    // progress tick first. None once the done event has gone out.
+    async fn next_events(&mut self) -> Option<String> {
+        if self.done {
+            return None;
+        }
+        if self.status.is_some() {
+            tokio::select! {
+                _ = self.updates.changed() => {}
+                _ = sleep(EVENT_PROGRESS_INTERVAL) => {}
+            }
+        }
+
+        let mut job = self.job_ref.lock().await;
+        let mut response = status_response(&self.data, &self.job_id, &mut job, self.format);
+        let mut events = String::new();
+
+        for result in job.results.iter().skip(self.sent) {
+            let result = result.clone().with_format(self.format).unwrap();
+            events.push_str(&JobEvent::new(JobEventKind::Result, &result).to_sse());
+        }
+        self.sent = job.results.len();
+
+        if job.end_time.is_some() || job.evicted {
+            if job.end_time.is_none() {
+                response.status = "error".to_string();
+                response.error = Some("The job was removed by the server".to_string());
+            }
+            events.push_str(&JobEvent::new(JobEventKind::Done, &response).to_sse());
+            self.done = true;
+        } else if self.status.as_ref() != Some(&response.status) {
+            self.status = Some(response.status.clone());
+            events.push_str(&JobEvent::new(JobEventKind::Status, &response).to_sse());
+        } else {
+            events.push_str(&JobEvent::new(JobEventKind::Progress, &response).to_sse());
+        }
+        Some(events)
+    }
+}
+
+// Stream a job's progress as server-sent events: status changes, progress
+// about once a second, each match as it is found, and a final done event
+async fn job_events(
+    path: web::Path<String>,
+    query: web::Query<StatusQuery>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+    let format = match key_format(&query) {
+        Ok(format) => format,
+        Err(err) => return HttpResponse::BadRequest().json(serde_json::json!({ "error": err })),
+    };
+    let Some(job_ref) = data.jobs.get(&job_id).map(|entry| entry.value().clone()) else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Job not found"
+        }));
+    };
+    let updates = job_ref.lock().await.updates.subscribe();
+
+    let stream = EventStream {
+        data: data.clone(),
+        job_id,
+        job_ref,
+        format,
+        updates,
+        status: None,
+        sent: 0,
+        done: false,
+    };
+    let body = futures_util::stream::unfold(stream, |mut stream| async move {
+        let events = stream.next_events().await?;
+        Some((Ok::<_, actix_web::Error>(web::Bytes::from(events)), stream))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
 // Cancel a job
 async fn cancel_job(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
     let job_id = path.into_inner();
 
     match data.jobs.get(&job_id) {
         Some(job_ref) => {
-            let job = job_ref.lock().await;
+            let mut job = job_ref.lock().await;
             job.cancel_flag.store(true, Ordering::Relaxed);
 
+            // A queued job never reaches a worker, so end it here
+            if data.scheduler.lock().unwrap().dequeue(&job_id).is_some() {
+                job.status = "error".to_string();
+                job.error = Some("Operation was canceled".to_string());
+                job.end_time = Some(Instant::now());
+                persist(data.store.as_ref(), &job_id, &job);
+            }
+
             HttpResponse::Ok().json(serde_json::json!({
                 "status": "cancellation_requested"
             }))
@@ -231,38 +907,70 @@ async fn health_check() -> impl Responder {
     })
 }
 
-// Cleanup task to remove old jobs
+// Cleanup task to evict jobs past their retention, stopping any search
+// still running for them
 async fn cleanup_old_jobs(data: web::Data<AppState>) {
     loop {
-        sleep(Duration::from_secs(3600)).await; // Run every hour
+        sleep(data.retention.interval).await;
 
         let now = Instant::now();
-        let to_remove: Vec<String> = data
-            .jobs
-            .iter()
-            .filter_map(|entry| {
-                let job = entry.value().blocking_lock();
-                if now.duration_since(job.start_time).as_secs() > 86400 {
-                    // Remove jobs older than 24 hours
-                    Some(entry.key().clone())
-                } else {
-                    None
-                }
-            })
-            .collect();
+        for (job_id, job_ref) in job_refs(&data) {
+            let mut job = job_ref.lock().await;
+            if !data.retention.is_expired(&job, now) {
+                continue;
+            }
+            job.evicted = true;
+            job.cancel_flag.store(true, Ordering::Relaxed);
+            job.updates.send_replace(());
+            data.scheduler.lock().unwrap().dequeue(&job_id);
+            drop(job);
 
-        for job_id in to_remove {
             data.jobs.remove(&job_id);
+            if let Err(err) = data.store.remove(&job_id) {
+                eprintln!("⚠️  Couldn't remove job {} from the store: {}", job_id, err);
+            }
         }
     }
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let config = match config_flags()
+        .and_then(|(config_file, flags)| ServerConfig::load(config_file.as_deref(), flags))
+    {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("❌ Invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+    // After loading, so RUST_LOG may come from .env
+    env_logger::init();
+
+    let rates = HashRates::measure();
+    let banner_rates = format!(
+        "{:.0} keys/s ({:.0}/s from seed phrases, {:.0}/s for PDAs, {:.0}/s for derived accounts, {:.0}/s for token accounts, {:.0}/s for split keys)",
+        rates.keys,
+        rates.mnemonics,
+        rates.program_addresses,
+        rates.derived_accounts,
+        rates.token_accounts,
+        rates.split_keys
+    );
+
+    // Open the job store; without it jobs would silently vanish on restart
+    let store = SledJobStore::open(&config.data_dir).map_err(std::io::Error::other)?;
+
     // Initialize state
     let app_state = web::Data::new(AppState {
         jobs: DashMap::new(),
+        store: Arc::new(store),
+        retention: Retention::new(&config),
+        scheduler: std::sync::Mutex::new(Scheduler::new(&config)),
+        rates,
     });
+    let resumed = restore_jobs(&app_state).map_err(std::io::Error::other)?;
+    run_queued(&app_state);
 
     // Start cleanup task
     let state_for_cleanup = app_state.clone();
@@ -270,6 +978,12 @@ async fn main() -> std::io::Result<()> {
         cleanup_old_jobs(state_for_cleanup).await;
     });
 
+    // Start the task that checkpoints running jobs
+    let state_for_saving = app_state.clone();
+    tokio::spawn(async move {
+        save_running_jobs(state_for_saving).await;
+    });
+
     println!("\n");
     println!(" ███████╗ ██████╗ ██╗      █████╗ ███╗   ██╗ █████╗     ██╗   ██╗ █████╗ ███╗   ██╗██╗████████╗██╗   ██╗");
     println!(" ██╔════╝██╔═══██╗██║     ██╔══██╗████╗  ██║██╔══██╗    ██║   ██║██╔══██╗████╗  ██║██║╚══██╔══╝╚██╗ ██╔╝");
@@ -282,8 +996,16 @@ async fn main() -> std::io::Result<()> {
     println!();
 
     // Bind server
-    let server_address = "127.0.0.1:3001";
+    let server_address = config.bind_address();
     println!("🚀 Server starting on: http://{}", server_address);
+    println!("⚡ Measured hash rate: {}", banner_rates);
+    println!(
+        "💾 Job store: {} jobs loaded, {} resumed",
+        app_state.jobs.len(),
+        resumed
+    );
+    println!("⚙️  Configuration:");
+    println!("{}", config);
     println!();
 
     // Print new CLI usage instructions
@@ -292,10 +1014,12 @@ async fn main() -> std::io::Result<()> {
     println!("  2. Open a new terminal and run one of these commands:");
     println!();
     println!(
-        "     \x1b[1;32m./run_cli.sh abc prefix\x1b[0m    # Generate address starting with 'abc'"
+        "     \x1b[1;32m./run_cli.sh abc prefix --server http://{}\x1b[0m    # Generate address starting with 'abc'",
+        server_address
     );
     println!(
-        "     \x1b[1;32m./run_cli.sh xyz suffix\x1b[0m    # Generate address ending with 'xyz'"
+        "     \x1b[1;32m./run_cli.sh xyz suffix --server http://{}\x1b[0m    # Generate address ending with 'xyz'",
+        server_address
     );
     println!();
     println!("     Replace 'abc' or 'xyz' with your desired 3-8 character pattern");
@@ -319,25 +1043,139 @@ async fn main() -> std::io::Result<()> {
     );
     println!("    {{\"status\":\"complete\",\"result\":{{\"public_key\":\"abc...\",\"private_key\":\"...\"}}}}", );
     println!();
+    println!("  Estimate how long a pattern will take before submitting it:");
+    println!("    curl -X POST http://{}/estimate -H \"Content-Type: application/json\" -d '{{\"pattern\":\"abcdef\",\"position\":\"prefix\"}}'", server_address);
+    println!();
 
     // Start server
+    let store = app_state.store.clone();
     HttpServer::new(move || {
         // Configure CORS
-        let cors = Cors::default()
-            .allow_any_origin()
-            .allow_any_method()
-            .allow_any_header()
-            .max_age(3600);
+        let cors = if config.cors_origins.is_empty() {
+            Cors::default().allow_any_origin()
+        } else {
+            config
+                .cors_origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        };
+        let cors = cors.allow_any_method().allow_any_header().max_age(3600);
 
-        App::new()
+        WebApp::new()
             .wrap(cors)
             .app_data(app_state.clone())
             .route("/generate", web::post().to(generate_address))
+            .route("/estimate", web::post().to(estimate))
             .route("/status/{job_id}", web::get().to(get_status))
+            .route("/jobs/{job_id}/events", web::get().to(job_events))
             .route("/cancel/{job_id}", web::post().to(cancel_job))
             .route("/health", web::get().to(health_check))
     })
-    .bind(server_address)?
+    // Event streams stay open until their job ends, so don't wait long for them
+    .shutdown_timeout(5)
+    .bind(&server_address)?
     .run()
-    .await
+    .await?;
+
+    // Saves reach the disk in the background; don't lose the last ones
+    store.flush().map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    fn retention() -> Retention {
+        Retention {
+            interval: Duration::from_secs(60),
+            complete: 24 * HOUR,
+            retrieved: HOUR / 6,
+            error: 7 * 24 * HOUR,
+            running: 24 * HOUR,
+        }
+    }
+
+    fn job(status: &str, start_time: Instant) -> Job {
+        Job {
+            status: status.to_string(),
+            request: VanityRequest::new("a", VanityPosition::Prefix),
+            recipient: None,
+            priority: 0,
+            created_at: 0,
+            start_time,
+            queued_at: None,
+            end_time: None,
+            cancel_flag: Arc::new(AtomicBool::new(false)),
+            attempts: Arc::new(AtomicU64::new(0)),
+            expected_attempts: None,
+            results: Vec::new(),
+            error: None,
+            retrieved_at: None,
+            evicted: false,
+            updates: watch::channel(()).0,
+        }
+    }
+
+    #[test]
+    fn running_jobs_expire_after_the_running_ttl() {
+        let start = Instant::now();
+        let job = job("running", start);
+        let retention = retention();
+        assert!(!retention.is_expired(&job, start + 24 * HOUR));
+        assert!(retention.is_expired(&job, start + 25 * HOUR));
+    }
+
+    #[test]
+    fn pending_jobs_dont_count_time_in_the_queue() {
+        let start = Instant::now();
+        let retention = retention();
+
+        let mut waiting = job("pending", start);
+        waiting.queued_at = Some(start);
+        assert!(!retention.is_expired(&waiting, start + 100 * HOUR));
+
+        // Requeued after a restart with 23 hours already searched
+        let mut resumed = job("pending", start);
+        resumed.queued_at = Some(start + 23 * HOUR);
+        assert!(!retention.is_expired(&resumed, start + 100 * HOUR));
+        resumed.queued_at = Some(start + 25 * HOUR);
+        assert!(retention.is_expired(&resumed, start + 25 * HOUR));
+    }
+
+    #[test]
+    fn failed_jobs_expire_after_the_error_ttl() {
+        let start = Instant::now();
+        let mut job = job("error", start);
+        job.end_time = Some(start + HOUR);
+        let retention = retention();
+        assert!(!retention.is_expired(&job, start + 7 * 24 * HOUR));
+        assert!(retention.is_expired(&job, start + 7 * 24 * HOUR + 2 * HOUR));
+    }
+
+    #[test]
+    fn finished_jobs_expire_after_the_complete_ttl() {
+        let start = Instant::now();
+        let mut job = job("complete", start);
+        job.end_time = Some(start + HOUR);
+        let retention = retention();
+        assert!(!retention.is_expired(&job, start + 24 * HOUR));
+        assert!(retention.is_expired(&job, start + 26 * HOUR));
+    }
+
+    #[test]
+    fn retrieved_jobs_expire_after_the_retrieved_ttl() {
+        let start = Instant::now();
+        let mut job = job("complete", start);
+        job.end_time = Some(start + HOUR);
+        job.retrieved_at = Some(start + 2 * HOUR);
+        let retention = retention();
+        assert!(!retention.is_expired(&job, start + 2 * HOUR + HOUR / 6));
+        assert!(retention.is_expired(&job, start + 3 * HOUR));
+
+        // Results fetched late get the same grace period from the fetch
+        job.retrieved_at = Some(start + 30 * HOUR);
+        assert!(!retention.is_expired(&job, start + 30 * HOUR + HOUR / 12));
+    }
 }
@@ -0,0 +1,1009 @@
+use crate::estimate::prefix_probability;
+use crate::generator::{VanityPosition, VanityRequest, MAX_COUNT};
+use crate::prefilter::{PrefixRanges, SuffixResidues};
+use regex::{RegexSet, RegexSetBuilder};
+use regex_syntax::hir::{Class, Hir, HirKind};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The characters that can appear in a base58-encoded Solana address.
+pub const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// Regex class matching exactly one base58 character
+const BASE58_CLASS: &str = "[1-9A-HJ-NP-Za-km-z]";
+
+// Leading characters whose odds depend on how long the address encodes to
+const SKEWED_PREFIX_LEN: usize = 3;
+
+// Length of the address of the largest 32-byte keys
+const MAX_ADDRESS_LEN: usize = 44;
+
+// Case variants of a pattern worth turning into a raw-key prefilter
+const MAX_PREFILTER_VARIANTS: usize = 1024;
+
+// Case variants of a prefix worth tracking before giving up on proving it
+// impossible
+const MAX_PREFIX_VARIANTS: usize = 4096;
+
+/// How the pattern of a [`VanityRequest`] is interpreted.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// The pattern is compared literally.
+    #[default]
+    Exact,
+    /// `?` matches any one base58 character, `*` any run of them, and
+    /// `[...]` a set of characters, e.g. `So?ana*`.
+    Glob,
+    /// The pattern is a regular expression.
+    Regex,
+}
+
+impl MatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMode::Exact => "exact",
+            MatchMode::Glob => "glob",
+            MatchMode::Regex => "regex",
+        }
+    }
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MatchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(MatchMode::Exact),
+            "glob" => Ok(MatchMode::Glob),
+            "regex" => Ok(MatchMode::Regex),
+            _ => Err("Mode must be 'exact', 'glob' or 'regex'".to_string()),
+        }
+    }
+}
+
+/// A character that stops an exact pattern from ever appearing where the
+/// request needs it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PatternError {
+    /// The pattern as submitted.
+    pub pattern: String,
+    /// Zero-based position of the offending character within `pattern`.
+    pub index: usize,
+    pub character: char,
+    /// Why the character can't appear there.
+    pub reason: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pattern '{}': character '{}' at position {} {}",
+            self.pattern,
+            self.character,
+            self.index + 1,
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// A [`VanityRequest`] compiled into a form that can be tested cheaply
+/// against many addresses.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    patterns: Vec<String>,
+    // The first constraint holds the candidate patterns; any others (the
+    // suffix of a combined search) must also match
+    constraints: Vec<Constraint>,
+}
+
+// A set of alternative patterns pinned to one end of the address
+#[derive(Debug, Clone)]
+struct Constraint {
+    case_sensitive: bool,
+    patterns: Vec<String>,
+    kind: MatcherKind,
+    prefilter: Option<Prefilter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Anchor {
+    Start,
+    End,
+}
+
+#[derive(Debug, Clone)]
+enum MatcherKind {
+    Exact(Trie),
+    Regex(RegexSet),
+}
+
+// Rules out most keys from their raw bytes, before they are encoded
+#[derive(Debug, Clone)]
+enum Prefilter {
+    Prefix(PrefixRanges),
+    Suffix(SuffixResidues),
+}
+
+impl Matcher {
+    /// Validates and compiles `request`.
+    pub fn new(request: &VanityRequest) -> Result<Self, String> {
+        if request.count == 0 {
+            return Err("Count must be at least 1".to_string());
+        }
+        if request.count > MAX_COUNT {
+            return Err(format!("Count can be at most {}", MAX_COUNT));
+        }
+        if let Some(mnemonic) = &request.mnemonic {
+            mnemonic.validate()?;
+        }
+        if let Some(pda) = &request.pda {
+            pda.validate()?;
+        }
+        if let Some(derived_account) = &request.derived_account {
+            derived_account.validate()?;
+        }
+        if let Some(split_key) = &request.split_key {
+            split_key.validate()?;
+        }
+        let sources = [
+            request.mnemonic.is_some(),
+            request.pda.is_some(),
+            request.derived_account.is_some(),
+            request.split_key.is_some(),
+        ];
+        if sources.into_iter().filter(|&source| source).count() > 1 {
+            return Err(
+                "Choose one of a seed phrase, a PDA, a derived account or a split key search"
+                    .to_string(),
+            );
+        }
+        if let Some(token_account) = &request.token_account {
+            if request.pda.is_some()
+                || request.derived_account.is_some()
+                || request.split_key.is_some()
+            {
+                return Err("Token account searches need wallet keypairs".to_string());
+            }
+            token_account.validate()?;
+        }
+        check_patterns(request).map_err(|err| err.to_string())?;
+
+        let patterns: Vec<String> = request.candidates().map(str::to_string).collect();
+
+        let constraints = match (request.position, &request.suffix) {
+            (VanityPosition::Prefix, None) => {
+                vec![Constraint::new(&patterns, request, Anchor::Start)?]
+            }
+            (VanityPosition::Suffix, None) => {
+                vec![Constraint::new(&patterns, request, Anchor::End)?]
+            }
+            (VanityPosition::Both, Some(suffix)) => vec![
+                Constraint::new(&patterns, request, Anchor::Start)?,
+                Constraint::new(std::slice::from_ref(suffix), request, Anchor::End)?,
+            ],
+            (VanityPosition::Both, None) => {
+                return Err("Position 'both' requires a suffix".to_string());
+            }
+            (_, Some(_)) => {
+                return Err("A separate suffix is only allowed with position 'both'".to_string());
+            }
+        };
+
+        Ok(Self {
+            patterns,
+            constraints,
+        })
+    }
+
+    /// Cheap test on a raw public key. `false` means its address can't match;
+    /// `true` means it might, and [`Matcher::is_match`] has the final say.
+    pub fn may_match(&self, key: &[u8; 32]) -> bool {
+        self.constraints
+            .iter()
+            .all(|constraint| match &constraint.prefilter {
+                Some(Prefilter::Prefix(ranges)) => ranges.contains(key),
+                Some(Prefilter::Suffix(residues)) => residues.contains(key),
+                None => true,
+            })
+    }
+
+    /// Returns true if `address` satisfies the request.
+    pub fn is_match(&self, address: &str) -> bool {
+        self.find(address).is_some()
+    }
+
+    /// Returns the candidate pattern that `address` matched, if any.
+    pub fn matched_pattern(&self, address: &str) -> Option<&str> {
+        self.find(address)
+            .map(|index| self.patterns[index].as_str())
+    }
+
+    // Index into `patterns` of the first candidate that matches
+    fn find(&self, address: &str) -> Option<usize> {
+        let (candidates, rest) = self.constraints.split_first()?;
+        let index = candidates.find(address)?;
+        if rest
+            .iter()
+            .all(|constraint| constraint.find(address).is_some())
+        {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Mean number of random keypairs needed to find a match, or `None` when
+    /// the pattern is a glob or regular expression.
+    pub fn expected_attempts(&self) -> Option<f64> {
+        self.constraints
+            .iter()
+            .map(Constraint::expected_attempts)
+            .product()
+    }
+}
+
+impl Constraint {
+    fn new(patterns: &[String], request: &VanityRequest, anchor: Anchor) -> Result<Self, String> {
+        if patterns.iter().any(String::is_empty) {
+            return Err("Pattern cannot be empty".to_string());
+        }
+
+        let case_sensitive = request.case_sensitive;
+        let prefilter = match request.mode {
+            MatchMode::Exact => Some(Prefilter::new(patterns, anchor, case_sensitive)),
+            MatchMode::Glob | MatchMode::Regex => None,
+        };
+        let kind = match request.mode {
+            MatchMode::Exact => MatcherKind::Exact(Trie::new(patterns, anchor, case_sensitive)),
+            MatchMode::Glob => {
+                let regexes = patterns
+                    .iter()
+                    .map(|pattern| glob_to_regex(pattern))
+                    .collect::<Result<Vec<_>, _>>()?;
+                MatcherKind::Regex(compile(&regexes, anchor, case_sensitive)?)
+            }
+            MatchMode::Regex => MatcherKind::Regex(compile(patterns, anchor, case_sensitive)?),
+        };
+
+        Ok(Self {
+            case_sensitive,
+            patterns: patterns.to_vec(),
+            kind,
+            prefilter,
+        })
+    }
+
+    fn find(&self, address: &str) -> Option<usize> {
+        match &self.kind {
+            MatcherKind::Exact(trie) => trie.find(address),
+            MatcherKind::Regex(set) => set.matches(address).into_iter().next(),
+        }
+    }
+
+    // Counts every letter-case variant of each pattern that the alphabet
+    // allows. Key length only skews the first few characters of a prefix;
+    // past those, and anywhere in a suffix, each character is uniform over
+    // the 58. Alternatives are assumed not to overlap.
+    fn expected_attempts(&self) -> Option<f64> {
+        let MatcherKind::Exact(trie) = &self.kind else {
+            return None;
+        };
+
+        let probability: f64 = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                let spellings: Vec<Vec<char>> = pattern
+                    .chars()
+                    .map(|c| spellings(c, self.case_sensitive))
+                    .collect();
+                let skewed = match trie.anchor {
+                    Anchor::Start => spellings.len().min(SKEWED_PREFIX_LEN),
+                    Anchor::End => 0,
+                };
+                let (head, tail) = spellings.split_at(skewed);
+
+                let head_probability: f64 = if head.is_empty() {
+                    1.0
+                } else {
+                    variants(head)
+                        .iter()
+                        .map(|variant| prefix_probability(variant))
+                        .sum()
+                };
+                let tail_probability: f64 = tail
+                    .iter()
+                    .map(|options| options.len() as f64 / 58.0)
+                    .product();
+                head_probability * tail_probability
+            })
+            .sum();
+        if probability > 0.0 {
+            Some(1.0 / probability.min(1.0))
+        } else {
+            Some(f64::INFINITY)
+        }
+    }
+}
+
+impl Prefilter {
+    fn new(patterns: &[String], anchor: Anchor, case_sensitive: bool) -> Self {
+        let spelled: Vec<String> = patterns
+            .iter()
+            .flat_map(|pattern| {
+                let mut positions: Vec<Vec<char>> = pattern
+                    .chars()
+                    .map(|c| spellings(c, case_sensitive))
+                    .collect();
+                if let Anchor::End = anchor {
+                    positions.reverse();
+                }
+
+                // Keep only as many characters from the anchored end as spell
+                // a manageable number of variants; the filter stays a superset
+                let mut count = 1;
+                let kept = positions
+                    .iter()
+                    .take_while(|options| {
+                        count *= options.len();
+                        count <= MAX_PREFILTER_VARIANTS
+                    })
+                    .count();
+                positions.truncate(kept);
+                if let Anchor::End = anchor {
+                    positions.reverse();
+                }
+                variants(&positions)
+            })
+            .collect();
+
+        match anchor {
+            Anchor::Start => {
+                Prefilter::Prefix(PrefixRanges::new(spelled.iter().map(String::as_str)))
+            }
+            Anchor::End => {
+                Prefilter::Suffix(SuffixResidues::new(spelled.iter().map(String::as_str)))
+            }
+        }
+    }
+}
+
+/// Checks that every exact pattern in `request` can appear where it is
+/// anchored, pointing at the first character that rules it out.
+///
+/// Besides characters outside the base58 alphabet, this rejects patterns
+/// longer than any address and prefixes that no 32-byte key encodes to.
+/// Glob and regex patterns are checked when the [`Matcher`] compiles them.
+pub fn check_patterns(request: &VanityRequest) -> Result<(), PatternError> {
+    if request.mode != MatchMode::Exact {
+        return Ok(());
+    }
+
+    let anchor = match request.position {
+        VanityPosition::Prefix | VanityPosition::Both => Anchor::Start,
+        VanityPosition::Suffix => Anchor::End,
+    };
+    for pattern in request.candidates() {
+        check_exact(pattern, anchor, request.case_sensitive)?;
+    }
+    if let Some(suffix) = &request.suffix {
+        check_exact(suffix, Anchor::End, request.case_sensitive)?;
+    }
+    Ok(())
+}
+
+fn check_exact(pattern: &str, anchor: Anchor, case_sensitive: bool) -> Result<(), PatternError> {
+    let error = |index: usize, character: char, reason: String| PatternError {
+        pattern: pattern.to_string(),
+        index,
+        character,
+        reason,
+    };
+
+    // Prefixes of the pattern so far, in every spelling some key encodes to
+    let mut viable = Some(vec![String::new()]);
+    for (index, c) in pattern.chars().enumerate() {
+        let options = spellings(c, case_sensitive);
+        if options.is_empty() {
+            let reason = if BASE58_ALPHABET.contains(c.to_ascii_lowercase())
+                || BASE58_ALPHABET.contains(c.to_ascii_uppercase())
+            {
+                "only appears in base58 with the other letter case".to_string()
+            } else {
+                "is not in the base58 alphabet, which leaves out 0, O, I and l".to_string()
+            };
+            return Err(error(index, c, reason));
+        }
+        if index >= MAX_ADDRESS_LEN {
+            return Err(error(
+                index,
+                c,
+                format!(
+                    "is past the end of the longest address ({} characters)",
+                    MAX_ADDRESS_LEN
+                ),
+            ));
+        }
+
+        // Suffixes draw on the uniform low digits of the key
+        let (Anchor::Start, Some(prefixes)) = (anchor, viable.as_mut()) else {
+            continue;
+        };
+        if prefixes.len() * options.len() > MAX_PREFIX_VARIANTS {
+            // Too many spellings to rule out; assume the rest is possible
+            viable = None;
+            continue;
+        }
+        *prefixes = prefixes
+            .iter()
+            .flat_map(|prefix| {
+                options.iter().map(move |option| {
+                    let mut prefix = prefix.clone();
+                    prefix.push(*option);
+                    prefix
+                })
+            })
+            .filter(|prefix| prefix_probability(prefix) > 0.0)
+            .collect();
+        if prefixes.is_empty() {
+            let leading: String = pattern.chars().take(index + 1).collect();
+            return Err(error(
+                index,
+                c,
+                format!(
+                    "can't follow the characters before it: no 32-byte key has an address starting with '{}'",
+                    leading
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The base58 characters `c` matches at one position
+fn spellings(c: char, case_sensitive: bool) -> Vec<char> {
+    let mut options = vec![c];
+    if c.is_ascii_alphabetic() && !case_sensitive {
+        options = vec![c.to_ascii_lowercase(), c.to_ascii_uppercase()];
+    }
+    options.retain(|option| BASE58_ALPHABET.contains(*option));
+    options
+}
+
+// Every string spelled by picking one option per position
+fn variants(positions: &[Vec<char>]) -> Vec<String> {
+    positions
+        .iter()
+        .fold(vec![String::new()], |variants, options| {
+            variants
+                .iter()
+                .flat_map(|variant| {
+                    options.iter().map(move |c| {
+                        let mut variant = variant.clone();
+                        variant.push(*c);
+                        variant
+                    })
+                })
+                .collect()
+        })
+}
+
+// Byte trie over exact patterns, walked from the anchored end of the address
+// so all candidates are checked in a single pass
+#[derive(Debug, Clone)]
+struct Trie {
+    anchor: Anchor,
+    case_sensitive: bool,
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    children: Vec<(u8, usize)>,
+    pattern: Option<usize>,
+}
+
+impl Trie {
+    fn new(patterns: &[String], anchor: Anchor, case_sensitive: bool) -> Self {
+        let mut trie = Self {
+            anchor,
+            case_sensitive,
+            nodes: vec![TrieNode::default()],
+        };
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let bytes = pattern.as_bytes();
+            let mut node = 0;
+            for i in 0..bytes.len() {
+                let byte = trie.byte_at(bytes, i);
+                node = match trie.child(node, byte) {
+                    Some(child) => child,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.push((byte, child));
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].pattern.get_or_insert(index);
+        }
+
+        trie
+    }
+
+    // The `i`th byte counting from the anchored end, folded when case is
+    // ignored
+    fn byte_at(&self, bytes: &[u8], i: usize) -> u8 {
+        let byte = match self.anchor {
+            Anchor::Start => bytes[i],
+            Anchor::End => bytes[bytes.len() - 1 - i],
+        };
+        if self.case_sensitive {
+            byte
+        } else {
+            byte.to_ascii_lowercase()
+        }
+    }
+
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(b, _)| *b == byte)
+            .map(|&(_, child)| child)
+    }
+
+    fn find(&self, address: &str) -> Option<usize> {
+        let bytes = address.as_bytes();
+        let mut node = 0;
+        for i in 0..bytes.len() {
+            if let Some(index) = self.nodes[node].pattern {
+                return Some(index);
+            }
+            node = self.child(node, self.byte_at(bytes, i))?;
+        }
+        self.nodes[node].pattern
+    }
+}
+
+fn invalid_char(c: char) -> String {
+    format!("Character '{}' can never appear in a base58 address", c)
+}
+
+// Validates each expression and anchors it at the requested end of the
+// address
+fn compile(patterns: &[String], anchor: Anchor, case_sensitive: bool) -> Result<RegexSet, String> {
+    let anchored = patterns
+        .iter()
+        .map(|pattern| {
+            check_regex(pattern, case_sensitive)?;
+            Ok(match anchor {
+                Anchor::Start => format!("^(?:{})", pattern),
+                Anchor::End => format!("(?:{})$", pattern),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    RegexSetBuilder::new(anchored)
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| format!("Invalid pattern: {}", e))
+}
+
+// Translates a glob into an equivalent regular expression
+fn glob_to_regex(glob: &str) -> Result<String, String> {
+    let mut regex = String::new();
+    let mut chars = glob.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '?' => regex.push_str(BASE58_CLASS),
+            '*' => {
+                regex.push_str(BASE58_CLASS);
+                regex.push('*');
+            }
+            '[' => {
+                regex.push('[');
+                let mut first = true;
+                loop {
+                    match chars.next() {
+                        Some(']') if !first => break,
+                        Some('!') if first => regex.push('^'),
+                        Some('-') if !first => regex.push('-'),
+                        Some(c) => regex.push_str(&regex::escape(&c.to_string())),
+                        None => return Err("Unterminated '[' in glob pattern".to_string()),
+                    }
+                    first = false;
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    Ok(regex)
+}
+
+// Rejects expressions containing characters that no address can contain.
+// Unless matching is case-sensitive, letters are folded to both cases first,
+// the same way the search compares them.
+fn check_regex(pattern: &str, case_sensitive: bool) -> Result<(), String> {
+    let hir = regex_syntax::ParserBuilder::new()
+        .case_insensitive(!case_sensitive)
+        .build()
+        .parse(pattern)
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+    check_hir(&hir)
+}
+
+fn check_hir(hir: &Hir) -> Result<(), String> {
+    match hir.kind() {
+        HirKind::Literal(literal) => {
+            String::from_utf8_lossy(&literal.0)
+                .chars()
+                .try_for_each(|c| {
+                    if BASE58_ALPHABET.contains(c) {
+                        Ok(())
+                    } else {
+                        Err(invalid_char(c))
+                    }
+                })
+        }
+        HirKind::Class(Class::Unicode(class)) => {
+            let possible = class.iter().any(|range| {
+                BASE58_ALPHABET
+                    .chars()
+                    .any(|c| range.start() <= c && c <= range.end())
+            });
+            if possible {
+                Ok(())
+            } else {
+                Err(no_base58_class(hir))
+            }
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let possible = class.iter().any(|range| {
+                BASE58_ALPHABET
+                    .bytes()
+                    .any(|b| range.start() <= b && b <= range.end())
+            });
+            if possible {
+                Ok(())
+            } else {
+                Err(no_base58_class(hir))
+            }
+        }
+        HirKind::Repetition(repetition) => check_hir(&repetition.sub),
+        HirKind::Capture(capture) => check_hir(&capture.sub),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => hirs.iter().try_for_each(check_hir),
+        HirKind::Empty | HirKind::Look(_) => Ok(()),
+    }
+}
+
+fn no_base58_class(hir: &Hir) -> String {
+    format!("'{}' matches no base58 character", hir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signer;
+    use std::sync::atomic::AtomicBool;
+
+    fn prefix(pattern: &str) -> VanityRequest {
+        VanityRequest::new(pattern, VanityPosition::Prefix).with_case_sensitive(true)
+    }
+
+    #[test]
+    fn pattern_errors_name_the_character() {
+        let err = check_patterns(&prefix("s0l")).unwrap_err();
+        assert_eq!(err.pattern, "s0l");
+        assert_eq!(err.index, 1);
+        assert_eq!(err.character, '0');
+        assert_eq!(
+            err.reason,
+            "is not in the base58 alphabet, which leaves out 0, O, I and l"
+        );
+        assert_eq!(
+            err.to_string(),
+            "Pattern 's0l': character '0' at position 2 is not in the base58 alphabet, which leaves out 0, O, I and l"
+        );
+        assert_eq!(Matcher::new(&prefix("s0l")).unwrap_err(), err.to_string());
+    }
+
+    #[test]
+    fn rejects_characters_base58_leaves_out() {
+        for c in ['0', 'O', 'I', 'l'] {
+            let err = check_patterns(&prefix(&format!("ab{}", c))).unwrap_err();
+            assert_eq!((err.index, err.character), (2, c));
+        }
+
+        // Only '0' has no other letter case to fall back on
+        let reason = |c: char| {
+            check_patterns(&prefix(&format!("ab{}", c)))
+                .unwrap_err()
+                .reason
+        };
+        assert!(reason('0').starts_with("is not in the base58 alphabet"));
+        for c in ['O', 'I', 'l'] {
+            assert_eq!(
+                reason(c),
+                "only appears in base58 with the other letter case"
+            );
+        }
+    }
+
+    #[test]
+    fn other_letter_case_is_accepted_unless_case_sensitive() {
+        for pattern in ["abO", "abI", "abl"] {
+            let request = VanityRequest::new(pattern, VanityPosition::Suffix);
+            assert!(check_patterns(&request).is_ok(), "{}", pattern);
+            assert!(check_patterns(&request.with_case_sensitive(true)).is_err());
+        }
+        let request = VanityRequest::new("ab0", VanityPosition::Suffix);
+        assert_eq!(check_patterns(&request).unwrap_err().index, 2);
+    }
+
+    #[test]
+    fn case_sensitive_l_never_matches_but_capital_l_does() {
+        assert!(Matcher::new(&prefix("Sol")).is_err());
+        let matcher = Matcher::new(&prefix("SoL")).unwrap();
+        assert!(matcher.is_match("SoLana1111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("Solana1111111111111111111111111111111111111"));
+    }
+
+    #[test]
+    fn rejects_prefixes_no_key_encodes_to() {
+        // The largest key, 32 0xff bytes, encodes to "JEKNVnkbo3jma5nR..."
+        let err = check_patterns(&prefix(&"z".repeat(44))).unwrap_err();
+        assert_eq!((err.index, err.character), (43, 'z'));
+        assert!(err
+            .reason
+            .starts_with("can't follow the characters before it"));
+
+        // 32 zero bytes encode to 32 '1's, with nothing after them
+        let err = check_patterns(&prefix(&format!("{}2", "1".repeat(32)))).unwrap_err();
+        assert_eq!((err.index, err.character), (32, '2'));
+
+        // The same characters are fine at the end, where every digit is uniform
+        let suffix = VanityRequest::new("z".repeat(44), VanityPosition::Suffix);
+        assert!(check_patterns(&suffix).is_ok());
+    }
+
+    #[test]
+    fn rejects_patterns_longer_than_any_address() {
+        for position in [VanityPosition::Prefix, VanityPosition::Suffix] {
+            let request = VanityRequest::new("2".repeat(45), position);
+            let err = check_patterns(&request).unwrap_err();
+            assert_eq!((err.index, err.character), (44, '2'));
+            assert!(err
+                .reason
+                .starts_with("is past the end of the longest address"));
+        }
+    }
+
+    #[test]
+    fn rejects_regex_classes_without_base58_characters() {
+        let regex = |pattern: &str, case_sensitive: bool| {
+            Matcher::new(
+                &VanityRequest::new(pattern, VanityPosition::Prefix)
+                    .with_mode(MatchMode::Regex)
+                    .with_case_sensitive(case_sensitive),
+            )
+        };
+
+        let err = regex("So[0OIl]", true).unwrap_err();
+        assert!(err.ends_with("matches no base58 character"), "{}", err);
+        assert!(regex("So[0]", false).is_err());
+        // Folded to 'o', 'i' and 'L'
+        assert!(regex("So[0OIl]", false).is_ok());
+
+        let glob = VanityRequest::new("So[0l]*", VanityPosition::Prefix)
+            .with_mode(MatchMode::Glob)
+            .with_case_sensitive(true);
+        assert!(Matcher::new(&glob).is_err());
+    }
+
+    fn glob(pattern: &str, position: VanityPosition) -> Matcher {
+        Matcher::new(
+            &VanityRequest::new(pattern, position)
+                .with_mode(MatchMode::Glob)
+                .with_case_sensitive(true),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn globs_translate_to_base58_regexes() {
+        assert_eq!(
+            glob_to_regex("So?ana*").unwrap(),
+            format!("So{0}ana{0}*", BASE58_CLASS)
+        );
+        assert_eq!(glob_to_regex("[abc]").unwrap(), "[abc]");
+        assert_eq!(glob_to_regex("[!ab]").unwrap(), "[^ab]");
+        assert_eq!(glob_to_regex("[a-f]x").unwrap(), "[a-f]x");
+        // A ']' straight after the '[' is part of the set
+        assert_eq!(glob_to_regex("[]a]").unwrap(), r"[\]a]");
+        assert_eq!(
+            glob_to_regex("[ab").unwrap_err(),
+            "Unterminated '[' in glob pattern"
+        );
+    }
+
+    #[test]
+    fn regex_metacharacters_in_globs_are_literal() {
+        assert_eq!(glob_to_regex("a.b+c").unwrap(), r"a\.b\+c");
+        assert_eq!(glob_to_regex("(x)|y^$").unwrap(), r"\(x\)\|y\^\$");
+        assert_eq!(glob_to_regex("[.]").unwrap(), r"[\.]");
+
+        // A literal '.' can't be in an address, so it never stands for any
+        // character
+        let request =
+            VanityRequest::new("So.ana", VanityPosition::Prefix).with_mode(MatchMode::Glob);
+        assert!(Matcher::new(&request).is_err());
+    }
+
+    #[test]
+    fn glob_wildcards_match_base58_runs() {
+        let matcher = glob("So?ana", VanityPosition::Prefix);
+        assert!(matcher.is_match("SoLana1111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("Soana11111111111111111111111111111111111111"));
+
+        // '*' may match nothing
+        let matcher = glob("So*na", VanityPosition::Prefix);
+        assert!(matcher.is_match("Sona111111111111111111111111111111111111111"));
+        assert!(matcher.is_match("SoXYZna1111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("SoXYZ11111111111111111111111111111111111111"));
+    }
+
+    #[test]
+    fn glob_classes_match_one_character() {
+        let matcher = glob("[AB]ob", VanityPosition::Prefix);
+        assert!(matcher.is_match("Aob11111111111111111111111111111111111111111"));
+        assert!(matcher.is_match("Bob11111111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("Cob11111111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("ABob1111111111111111111111111111111111111111"));
+
+        let matcher = glob("[!AB]ob", VanityPosition::Prefix);
+        assert!(matcher.is_match("Cob11111111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("Aob11111111111111111111111111111111111111111"));
+
+        let matcher = glob("[a-c]9", VanityPosition::Prefix);
+        assert!(matcher.is_match("b9111111111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("d9111111111111111111111111111111111111111111"));
+    }
+
+    #[test]
+    fn globs_are_anchored_to_their_end_of_the_address() {
+        let prefix = glob("pu?p", VanityPosition::Prefix);
+        assert!(prefix.is_match("puMp1111111111111111111111111111111111111111"));
+        assert!(!prefix.is_match("1111111111111111111111111111111111111111puMp"));
+        assert!(!prefix.is_match("111111111111111111111puMp11111111111111111111"));
+
+        let suffix = glob("pu?p", VanityPosition::Suffix);
+        assert!(suffix.is_match("1111111111111111111111111111111111111111puMp"));
+        assert!(!suffix.is_match("puMp1111111111111111111111111111111111111111"));
+        assert!(!suffix.is_match("111111111111111111111puMp11111111111111111111"));
+    }
+
+    #[test]
+    fn both_needs_the_prefix_and_the_suffix() {
+        let mut request = VanityRequest::new("Ab", VanityPosition::Both).with_case_sensitive(true);
+        request.suffix = Some("Yz".to_string());
+        let matcher = Matcher::new(&request).unwrap();
+
+        assert!(matcher.is_match("Ab1111111111111111111111111111111111111111Yz"));
+        assert!(!matcher.is_match("Ab111111111111111111111111111111111111111111"));
+        assert!(!matcher.is_match("111111111111111111111111111111111111111111Yz"));
+        // The ends don't swap
+        assert!(!matcher.is_match("Yz1111111111111111111111111111111111111111Ab"));
+        assert_eq!(
+            matcher.matched_pattern("Ab1111111111111111111111111111111111111111Yz"),
+            Some("Ab")
+        );
+        // Each end counts against the odds
+        let one_end = Matcher::new(&prefix("Ab")).unwrap();
+        assert!(matcher.expected_attempts().unwrap() > one_end.expected_attempts().unwrap() * 58.0);
+
+        // The search's raw-key prefilters keep to both ends as well
+        let mut request = VanityRequest::new("A", VanityPosition::Both).with_case_sensitive(true);
+        request.suffix = Some("z".to_string());
+        let keypair =
+            crate::generator::find_vanity_address(&request, &AtomicBool::new(false)).unwrap();
+        let address = keypair.pubkey().to_string();
+        assert!(
+            address.starts_with('A') && address.ends_with('z'),
+            "{}",
+            address
+        );
+
+        request.suffix = None;
+        assert_eq!(
+            Matcher::new(&request).unwrap_err(),
+            "Position 'both' requires a suffix"
+        );
+    }
+
+    #[test]
+    fn matched_pattern_is_the_candidate_that_matched() {
+        let request = prefix("SoL").with_patterns(vec!["Bob".to_string(), "So".to_string()]);
+        let matcher = Matcher::new(&request).unwrap();
+        assert_eq!(
+            matcher.matched_pattern("Bob1111111111111111111111111111111111111111"),
+            Some("Bob")
+        );
+        // Only the shorter candidate fits here
+        assert_eq!(
+            matcher.matched_pattern("SoX1111111111111111111111111111111111111111"),
+            Some("So")
+        );
+        // Both fit here, and whichever is reported must be a real match
+        let address = "SoLana1111111111111111111111111111111111111";
+        let matched = matcher.matched_pattern(address).unwrap();
+        assert!(address.starts_with(matched), "{}", matched);
+        assert_eq!(
+            matcher.matched_pattern("Xyz111111111111111111111111111111111111111"),
+            None
+        );
+    }
+
+    #[test]
+    fn matched_pattern_keeps_the_submitted_spelling() {
+        let request = VanityRequest::new("pump", VanityPosition::Suffix)
+            .with_patterns(vec!["ump".to_string(), "Moon".to_string()]);
+        let matcher = Matcher::new(&request).unwrap();
+        assert_eq!(
+            matcher.matched_pattern("111111111111111111111111111111111111111MOON"),
+            Some("Moon")
+        );
+        assert_eq!(
+            matcher.matched_pattern("111111111111111111111111111111111111111XUMP"),
+            Some("ump")
+        );
+        let address = "1111111111111111111111111111111111111111PUMP";
+        let matched = matcher.matched_pattern(address).unwrap();
+        assert!(address.to_lowercase().ends_with(matched), "{}", matched);
+    }
+
+    #[test]
+    fn matched_pattern_names_the_glob_or_regex_that_matched() {
+        let globs = VanityRequest::new("A*z", VanityPosition::Prefix)
+            .with_patterns(vec!["B?".to_string()])
+            .with_mode(MatchMode::Glob)
+            .with_case_sensitive(true);
+        let matcher = Matcher::new(&globs).unwrap();
+        assert_eq!(
+            matcher.matched_pattern("Bc11111111111111111111111111111111111111111"),
+            Some("B?")
+        );
+        assert_eq!(
+            matcher.matched_pattern("A11z111111111111111111111111111111111111111"),
+            Some("A*z")
+        );
+
+        let regexes = VanityRequest::new("[0-9]{3}", VanityPosition::Suffix)
+            .with_patterns(vec!["xyz".to_string()])
+            .with_mode(MatchMode::Regex);
+        let matcher = Matcher::new(&regexes).unwrap();
+        assert_eq!(
+            matcher.matched_pattern("11111111111111111111111111111111111111AXYZ"),
+            Some("xyz")
+        );
+        assert_eq!(
+            matcher.matched_pattern("11111111111111111111111111111111111111A123"),
+            Some("[0-9]{3}")
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Vanity program derived addresses.
+//!
+//! Instead of keypairs, a PDA search varies one seed after a fixed list of
+//! seeds until `Pubkey::find_program_address` lands on a matching address.
+//! The result has no private key; the program signs for it with the seeds
+//! and the canonical bump.
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN};
+use std::fmt;
+use std::str::FromStr;
+
+/// Search for a program derived address instead of a keypair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PdaOptions {
+    /// Base58 ID of the program that owns the address.
+    pub program_id: String,
+    /// Fixed seeds, as UTF-8 text, that come before the varied one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seeds: Vec<String>,
+    #[serde(default)]
+    pub variable: VariableSeed,
+    /// Length of a random varied seed, in bytes.
+    #[serde(default = "default_seed_len")]
+    pub seed_len: usize,
+    /// Where a counter seed starts counting, e.g. to carry on past the
+    /// matches of an earlier search.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub start_counter: u64,
+}
+
+fn default_seed_len() -> usize {
+    8
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// How the seed after the fixed ones is varied.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableSeed {
+    /// A `u64` counter as 8 little-endian bytes, counting up from
+    /// `start_counter`.
+    #[default]
+    Counter,
+    /// `seed_len` random bytes.
+    Random,
+}
+
+/// Seeds and bump that derive a matching program address.
+#[derive(Debug, Clone)]
+pub struct ProgramAddress {
+    /// Every seed, fixed ones first, without the bump.
+    pub seeds: Vec<Vec<u8>>,
+    pub bump: u8,
+    /// Value of the varied seed when it is a counter.
+    pub counter: Option<u64>,
+}
+
+impl PdaOptions {
+    pub fn new(program_id: impl Into<String>) -> Self {
+        Self {
+            program_id: program_id.into(),
+            seeds: Vec::new(),
+            variable: VariableSeed::Counter,
+            seed_len: default_seed_len(),
+            start_counter: 0,
+        }
+    }
+
+    /// Adds fixed seeds that precede the varied one.
+    pub fn with_seeds<I, S>(mut self, seeds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.seeds.extend(seeds.into_iter().map(Into::into));
+        self
+    }
+
+    /// Varies `seed_len` random bytes instead of a counter.
+    pub fn with_random_seed(mut self, seed_len: usize) -> Self {
+        self.variable = VariableSeed::Random;
+        self.seed_len = seed_len;
+        self
+    }
+
+    /// Starts a counter seed at `start_counter` instead of zero.
+    pub fn with_start_counter(mut self, start_counter: u64) -> Self {
+        self.start_counter = start_counter;
+        self
+    }
+
+    pub fn program_id(&self) -> Result<Pubkey, String> {
+        Pubkey::from_str(&self.program_id)
+            .map_err(|_| format!("Invalid program ID '{}'", self.program_id))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.program_id()?;
+        // The varied seed and the bump take two of the slots
+        if self.seeds.len() + 2 > MAX_SEEDS {
+            return Err(format!("At most {} fixed seeds are allowed", MAX_SEEDS - 2));
+        }
+        if let Some(seed) = self.seeds.iter().find(|seed| seed.len() > MAX_SEED_LEN) {
+            return Err(format!(
+                "Seed '{}' is longer than {} bytes",
+                seed, MAX_SEED_LEN
+            ));
+        }
+        if self.variable == VariableSeed::Random && !(1..=MAX_SEED_LEN).contains(&self.seed_len) {
+            return Err(format!(
+                "Random seeds must be 1-{} bytes long",
+                MAX_SEED_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl VariableSeed {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VariableSeed::Counter => "counter",
+            VariableSeed::Random => "random",
+        }
+    }
+}
+
+impl fmt::Display for VariableSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VariableSeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "counter" => Ok(VariableSeed::Counter),
+            "random" => Ok(VariableSeed::Random),
+            _ => Err("Variable seed must be 'counter' or 'random'".to_string()),
+        }
+    }
+}
+
+/// Per-thread source of candidate program addresses.
+pub struct PdaStream {
+    program_id: Pubkey,
+    seeds: Vec<Vec<u8>>,
+    variable: VariableSeed,
+    seed_len: usize,
+    rng: ChaCha20Rng,
+    // Workers interleave counters so none is tried twice
+    counter: u64,
+    step: u64,
+}
+
+/// The varied seed and bump of a candidate, kept until it turns out to match.
+#[derive(Debug, Clone, Copy)]
+pub struct PdaCandidate {
+    seed: [u8; MAX_SEED_LEN],
+    bump: u8,
+}
+
+impl PdaStream {
+    /// Starts worker `worker` of `workers` on the search `options` describes.
+    /// The options must be valid.
+    pub fn new(options: &PdaOptions, worker: usize, workers: usize) -> Self {
+        Self {
+            program_id: options.program_id().expect("PDA options are validated"),
+            seeds: options
+                .seeds
+                .iter()
+                .map(|seed| seed.clone().into_bytes())
+                .collect(),
+            variable: options.variable,
+            seed_len: match options.variable {
+                VariableSeed::Counter => 8,
+                VariableSeed::Random => options.seed_len,
+            },
+            rng: ChaCha20Rng::from_entropy(),
+            counter: options.start_counter.wrapping_add(worker as u64),
+            step: workers as u64,
+        }
+    }
+
+    /// Returns a fresh candidate's address bytes and the seed behind it.
+    pub fn next_candidate(&mut self) -> ([u8; 32], PdaCandidate) {
+        let mut seed = [0u8; MAX_SEED_LEN];
+        match self.variable {
+            VariableSeed::Counter => {
+                seed[..8].copy_from_slice(&self.counter.to_le_bytes());
+                self.counter = self.counter.wrapping_add(self.step);
+            }
+            VariableSeed::Random => self.rng.fill_bytes(&mut seed[..self.seed_len]),
+        }
+
+        let mut seeds: Vec<&[u8]> = self.seeds.iter().map(Vec::as_slice).collect();
+        seeds.push(&seed[..self.seed_len]);
+        let (address, bump) = Pubkey::find_program_address(&seeds, &self.program_id);
+        (address.to_bytes(), PdaCandidate { seed, bump })
+    }
+
+    /// Spells out the seeds of a candidate that matched.
+    pub fn program_address(&self, candidate: PdaCandidate) -> ProgramAddress {
+        let variable = &candidate.seed[..self.seed_len];
+        let counter = (self.variable == VariableSeed::Counter)
+            .then(|| u64::from_le_bytes(variable.try_into().unwrap()));
+
+        let mut seeds = self.seeds.clone();
+        seeds.push(variable.to_vec());
+        ProgramAddress {
+            seeds,
+            bump: candidate.bump,
+            counter,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{find_vanity_addresses, VanityPosition, VanityRequest};
+    use std::sync::atomic::{AtomicBool, AtomicU64};
+
+    fn program() -> String {
+        solana_sdk::system_program::id().to_string()
+    }
+
+    #[test]
+    fn matched_seeds_and_bump_rebuild_the_address() {
+        let options = [
+            PdaOptions::new(program()).with_seeds(["vault", "v1"]),
+            PdaOptions::new(program())
+                .with_seeds(["vault"])
+                .with_random_seed(12),
+        ];
+        for options in options {
+            let request = VanityRequest::new("a", VanityPosition::Prefix)
+                .with_count(2)
+                .with_pda(options.clone());
+            let found = find_vanity_addresses(
+                &request,
+                &AtomicBool::new(false),
+                &AtomicU64::new(0),
+                |_| {},
+            )
+            .unwrap();
+
+            for found in found {
+                let pda = found.program_address.unwrap();
+                let fixed: Vec<&[u8]> = options.seeds.iter().map(String::as_bytes).collect();
+                assert_eq!(pda.seeds.len(), fixed.len() + 1);
+                assert!(pda
+                    .seeds
+                    .iter()
+                    .zip(&fixed)
+                    .all(|(seed, fixed)| seed == fixed));
+                assert_eq!(
+                    pda.counter.is_some(),
+                    options.variable == VariableSeed::Counter
+                );
+
+                let bump = [pda.bump];
+                let mut seeds: Vec<&[u8]> = pda.seeds.iter().map(Vec::as_slice).collect();
+                seeds.push(&bump);
+                let rebuilt =
+                    Pubkey::create_program_address(&seeds, &options.program_id().unwrap()).unwrap();
+                assert_eq!(rebuilt, found.address);
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_seeds_are_limited_in_count_and_length() {
+        let fixed = |count: usize, len: usize| {
+            PdaOptions::new(program()).with_seeds((0..count).map(|_| "s".repeat(len)))
+        };
+        assert!(fixed(MAX_SEEDS - 2, MAX_SEED_LEN).validate().is_ok());
+        assert_eq!(
+            fixed(MAX_SEEDS - 1, 1).validate().unwrap_err(),
+            "At most 14 fixed seeds are allowed"
+        );
+        assert_eq!(
+            fixed(1, MAX_SEED_LEN + 1).validate().unwrap_err(),
+            format!("Seed '{}' is longer than 32 bytes", "s".repeat(33))
+        );
+    }
+
+    #[test]
+    fn random_seeds_must_fit_in_a_seed() {
+        let random = |len: usize| PdaOptions::new(program()).with_random_seed(len).validate();
+        assert!(random(1).is_ok());
+        assert!(random(MAX_SEED_LEN).is_ok());
+        for len in [0, MAX_SEED_LEN + 1] {
+            assert_eq!(
+                random(len).unwrap_err(),
+                "Random seeds must be 1-32 bytes long"
+            );
+        }
+        assert_eq!(
+            PdaOptions::new("not a program").validate().unwrap_err(),
+            "Invalid program ID 'not a program'"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Tests raw public key bytes against exact patterns without base58-encoding
+//! them.
+//!
+//! An address starts with a given prefix exactly when the key, read as a
+//! big-endian 256-bit integer, lies in one of a few intervals, and ends with
+//! a given suffix exactly when the key leaves a particular remainder modulo
+//! 58^k. Both are precomputed, so the hot loop only encodes keys that are
+//! very likely to match.
+
+use crate::matcher::BASE58_ALPHABET;
+
+// A key as a big-endian integer with one spare leading byte, so bounds up to
+// 58^44 (past the largest key) fit
+type Wide = [u8; 33];
+
+// Suffix characters folded into one remainder; 58^10 still fits in a u64
+const MAX_SUFFIX_DIGITS: usize = 10;
+
+/// Keys whose address starts with one of a set of exact prefixes.
+#[derive(Debug, Clone)]
+pub struct PrefixRanges {
+    // Sorted, non-overlapping half-open intervals
+    ranges: Vec<(Wide, Wide)>,
+}
+
+/// Keys whose address ends with one of a set of exact suffixes.
+#[derive(Debug, Clone)]
+pub struct SuffixResidues {
+    // One modulus per suffix length, with the sorted remainders it must leave
+    groups: Vec<(u64, Vec<u64>)>,
+}
+
+impl PrefixRanges {
+    /// Builds the intervals for `prefixes`, each spelled exactly as it must
+    /// appear (case-sensitive). Characters outside the alphabet never match.
+    pub fn new<'a>(prefixes: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut ranges: Vec<(Wide, Wide)> = prefixes
+            .into_iter()
+            .flat_map(|prefix| prefix_ranges(prefix).unwrap_or_default())
+            .collect();
+
+        ranges.sort();
+        let mut merged: Vec<(Wide, Wide)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            match merged.last_mut() {
+                Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+                _ => merged.push((lo, hi)),
+            }
+        }
+
+        Self { ranges: merged }
+    }
+
+    /// Returns true if the address of `key` starts with one of the prefixes.
+    pub fn contains(&self, key: &[u8; 32]) -> bool {
+        let mut wide = [0u8; 33];
+        wide[1..].copy_from_slice(key);
+
+        let next = self.ranges.partition_point(|(lo, _)| *lo <= wide);
+        next > 0 && wide < self.ranges[next - 1].1
+    }
+}
+
+impl SuffixResidues {
+    /// Builds the remainders for `suffixes`, each spelled exactly as it must
+    /// appear (case-sensitive). Characters outside the alphabet never match.
+    ///
+    /// Only the last ten characters are compared, so longer suffixes may let
+    /// through keys that don't end with them.
+    pub fn new<'a>(suffixes: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut groups: Vec<(u64, Vec<u64>)> = Vec::new();
+        for digits in suffixes.into_iter().filter_map(digits) {
+            let tail = &digits[digits.len().saturating_sub(MAX_SUFFIX_DIGITS)..];
+            let modulus = 58u64.pow(tail.len() as u32);
+            let residue = tail
+                .iter()
+                .fold(0u64, |residue, &digit| residue * 58 + digit as u64);
+
+            match groups.iter_mut().find(|(m, _)| *m == modulus) {
+                Some((_, residues)) => residues.push(residue),
+                None => groups.push((modulus, vec![residue])),
+            }
+        }
+
+        for (_, residues) in &mut groups {
+            residues.sort_unstable();
+            residues.dedup();
+        }
+        Self { groups }
+    }
+
+    /// Returns true if the address of `key` ends with one of the suffixes.
+    pub fn contains(&self, key: &[u8; 32]) -> bool {
+        self.groups.iter().any(|(modulus, residues)| {
+            // Eight bytes at a time keeps the intermediate below 2^123
+            let modulus = *modulus as u128;
+            let residue = key.chunks_exact(8).fold(0u128, |residue, chunk| {
+                let chunk = u64::from_be_bytes(chunk.try_into().unwrap()) as u128;
+                ((residue << 64) | chunk) % modulus
+            });
+            residues.binary_search(&(residue as u64)).is_ok()
+        })
+    }
+}
+
+// Base58 digit values of `text`, or `None` if a character isn't in the
+// alphabet
+fn digits(text: &str) -> Option<Vec<u8>> {
+    text.chars()
+        .map(|c| BASE58_ALPHABET.find(c).map(|digit| digit as u8))
+        .collect()
+}
+
+// The intervals of keys whose address starts with `prefix`
+fn prefix_ranges(prefix: &str) -> Option<Vec<(Wide, Wide)>> {
+    let ones = prefix.bytes().take_while(|&b| b == b'1').count();
+    let digits = digits(&prefix[ones..])?;
+    if ones >= 32 {
+        return None;
+    }
+
+    // `ones` zero bytes followed by a value whose top byte is not zero, so it
+    // encodes without any further leading '1'
+    let bits = 256 - 8 * ones;
+    let floor = power_of_two(bits - 8);
+    let total = power_of_two(bits);
+    if digits.is_empty() {
+        // Further zero bytes only add more '1's
+        return Some(vec![([0; 33], total)]);
+    }
+
+    // At each encoded length, the prefix and the number after it, both padded
+    // with zero digits, bound the run of matching keys
+    let mut next = digits.clone();
+    increment(&mut next);
+    let ranges = (digits.len()..=45)
+        .filter_map(|length| {
+            let lo = from_digits(&digits, length - digits.len())?.max(floor);
+            let hi = from_digits(&next, length - digits.len()).map_or(total, |hi| hi.min(total));
+            (lo < hi).then_some((lo, hi))
+        })
+        .collect();
+    Some(ranges)
+}
+
+// Adds one to a big-endian base58 number, growing it on overflow
+fn increment(digits: &mut Vec<u8>) {
+    for digit in digits.iter_mut().rev() {
+        if *digit == 57 {
+            *digit = 0;
+        } else {
+            *digit += 1;
+            return;
+        }
+    }
+    digits.insert(0, 1);
+}
+
+// `digits` followed by `zeros` zero digits, or `None` if that doesn't fit
+fn from_digits(digits: &[u8], zeros: usize) -> Option<Wide> {
+    let mut wide = [0u8; 33];
+    for digit in digits.iter().copied().chain(std::iter::repeat_n(0, zeros)) {
+        let mut carry = digit as u32;
+        for byte in wide.iter_mut().rev() {
+            let value = *byte as u32 * 58 + carry;
+            *byte = value as u8;
+            carry = value >> 8;
+        }
+        if carry > 0 {
+            return None;
+        }
+    }
+    Some(wide)
+}
+
+fn power_of_two(exponent: usize) -> Wide {
+    let mut wide = [0u8; 33];
+    wide[32 - exponent / 8] = 1 << (exponent % 8);
+    wide
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generator::{VanityPosition, VanityRequest};
+    use crate::matcher::Matcher;
+    use rand_core::{OsRng, RngCore};
+
+    // Random keys, every fourth run of them with up to three leading zero
+    // bytes so their addresses start with '1'
+    fn random_keys(count: usize) -> Vec<[u8; 32]> {
+        (0..count)
+            .map(|i| {
+                let mut key = [0u8; 32];
+                OsRng.fill_bytes(&mut key);
+                key[..i % 4].fill(0);
+                key
+            })
+            .collect()
+    }
+
+    fn address(key: &[u8; 32]) -> String {
+        bs58::encode(key).into_string()
+    }
+
+    // The prefilter must give the same answer as the full match, both on the
+    // keys the patterns were taken from and on unrelated ones
+    fn assert_agrees(request: &VanityRequest, keys: &[[u8; 32]]) {
+        let matcher = Matcher::new(request).unwrap();
+        for key in keys {
+            let address = address(key);
+            assert_eq!(
+                matcher.may_match(key),
+                matcher.is_match(&address),
+                "{:?} on {}",
+                request,
+                address
+            );
+        }
+    }
+
+    fn check_anchor(position: VanityPosition, case_sensitive: bool) {
+        let keys = random_keys(48);
+        for key in &keys {
+            let address = address(key);
+            for len in 1..=3 {
+                let pattern = match position {
+                    VanityPosition::Suffix => &address[address.len() - len..],
+                    _ => &address[..len],
+                };
+                let pattern = if case_sensitive {
+                    pattern.to_string()
+                } else {
+                    pattern.to_ascii_lowercase()
+                };
+                let request =
+                    VanityRequest::new(pattern, position).with_case_sensitive(case_sensitive);
+                assert_agrees(&request, &keys);
+            }
+        }
+    }
+
+    #[test]
+    fn prefix_ranges_agree_with_matching() {
+        check_anchor(VanityPosition::Prefix, true);
+    }
+
+    #[test]
+    fn suffix_residues_agree_with_matching() {
+        check_anchor(VanityPosition::Suffix, true);
+    }
+
+    #[test]
+    fn case_insensitive_prefilters_agree_with_matching() {
+        check_anchor(VanityPosition::Prefix, false);
+        check_anchor(VanityPosition::Suffix, false);
+    }
+
+    #[test]
+    fn leading_ones_agree_with_matching() {
+        let keys = random_keys(256);
+        for pattern in ["1", "11", "111", "1111", "12", "11A"] {
+            let request = VanityRequest::new(pattern, VanityPosition::Prefix);
+            assert_agrees(&request, &keys);
+        }
+    }
+
+    #[test]
+    fn both_ends_agree_with_matching() {
+        let keys = random_keys(48);
+        for key in &keys {
+            let address = address(key);
+            let request = VanityRequest::new(&address[..2], VanityPosition::Both)
+                .with_suffix(&address[address.len() - 1..]);
+            assert_agrees(&request, &keys);
+            assert!(Matcher::new(&request).unwrap().may_match(key));
+        }
+    }
+
+    #[test]
+    fn long_suffixes_never_reject_a_match() {
+        // Only the last ten characters are compared, so this is a superset
+        for key in random_keys(16) {
+            let address = address(&key);
+            let request =
+                VanityRequest::new(&address[address.len() - 12..], VanityPosition::Suffix)
+                    .with_case_sensitive(true);
+            assert!(Matcher::new(&request).unwrap().may_match(&key));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Decides which of the server's jobs run and how many cores each gets.
+
+use crate::config::ServerConfig;
+use crate::generator::WorkerLimit;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A job waiting for a free slot, with whatever its search needs in `job`.
+#[derive(Debug)]
+pub struct QueuedJob<T> {
+    pub job_id: String,
+    pub priority: i32,
+    pub job: T,
+}
+
+/// Runs at most `max_running_jobs` jobs at once and shares the cores evenly
+/// between them. The rest wait in a queue, highest priority first and in
+/// arrival order within a priority.
+#[derive(Debug)]
+pub struct Scheduler<T> {
+    max_running: usize,
+    max_queued: usize,
+    cores: usize,
+    queue: Vec<QueuedJob<T>>,
+    running: HashMap<String, Arc<WorkerLimit>>,
+}
+
+impl<T> Scheduler<T> {
+    pub fn new(config: &ServerConfig) -> Self {
+        Scheduler {
+            max_running: config.max_running_jobs,
+            max_queued: config.max_queued_jobs,
+            cores: config.threads,
+            queue: Vec::new(),
+            running: HashMap::new(),
+        }
+    }
+
+    /// How many jobs may wait at once.
+    pub fn max_queued(&self) -> usize {
+        self.max_queued
+    }
+
+    /// Whether another job would overflow the queue. Check this under the
+    /// same lock as the [`Scheduler::enqueue`] it guards.
+    pub fn is_full(&self) -> bool {
+        self.queue.len() >= self.max_queued
+    }
+
+    /// Queues `job` behind every job of the same or a higher priority.
+    pub fn enqueue(&mut self, job: QueuedJob<T>) {
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| queued.priority < job.priority)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(index, job);
+    }
+
+    /// Where a job stands in the queue, counting from 1.
+    pub fn position(&self, job_id: &str) -> Option<usize> {
+        self.queue
+            .iter()
+            .position(|queued| queued.job_id == job_id)
+            .map(|index| index + 1)
+    }
+
+    /// Takes a job out of the queue, e.g. when it is canceled before it
+    /// starts.
+    pub fn dequeue(&mut self, job_id: &str) -> Option<QueuedJob<T>> {
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| queued.job_id == job_id)?;
+        Some(self.queue.remove(index))
+    }
+
+    /// Jobs to start now that slots are free, each with its share of the
+    /// cores.
+    pub fn start_next(&mut self) -> Vec<(QueuedJob<T>, Arc<WorkerLimit>)> {
+        let mut started = Vec::new();
+        while self.running.len() < self.max_running && !self.queue.is_empty() {
+            let job = self.queue.remove(0);
+            let limit = Arc::new(WorkerLimit::with_capacity(1, self.cores));
+            self.running.insert(job.job_id.clone(), limit.clone());
+            started.push((job, limit));
+        }
+        if !started.is_empty() {
+            self.rebalance();
+        }
+        started
+    }
+
+    /// Frees the slot of a job that stopped running.
+    pub fn finish(&mut self, job_id: &str) {
+        if self.running.remove(job_id).is_some() {
+            self.rebalance();
+        }
+    }
+
+    /// How many workers a running job may keep busy.
+    pub fn workers(&self, job_id: &str) -> Option<usize> {
+        self.running.get(job_id).map(|limit| limit.get())
+    }
+
+    // Split the cores evenly; the first jobs take any left over
+    fn rebalance(&self) {
+        let running = self.running.len().max(1);
+        let (share, extra) = (self.cores / running, self.cores % running);
+        for (index, limit) in self.running.values().enumerate() {
+            limit.set(share + usize::from(index < extra));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigLayer;
+
+    fn scheduler(threads: usize, max_running: usize, max_queued: usize) -> Scheduler<()> {
+        let config = ServerConfig::from_layer(ConfigLayer {
+            threads: Some(threads),
+            max_running_jobs: Some(max_running),
+            max_queued_jobs: Some(max_queued),
+            ..ConfigLayer::default()
+        })
+        .unwrap();
+        Scheduler::new(&config)
+    }
+
+    fn queued(job_id: &str, priority: i32) -> QueuedJob<()> {
+        QueuedJob {
+            job_id: job_id.to_string(),
+            priority,
+            job: (),
+        }
+    }
+
+    fn started(scheduler: &mut Scheduler<()>) -> Vec<String> {
+        scheduler
+            .start_next()
+            .into_iter()
+            .map(|(job, _)| job.job_id)
+            .collect()
+    }
+
+    #[test]
+    fn higher_priorities_start_first_then_arrival_order() {
+        let mut scheduler = scheduler(4, 4, 10);
+        scheduler.enqueue(queued("low", 0));
+        scheduler.enqueue(queued("high", 5));
+        scheduler.enqueue(queued("low-2", 0));
+        scheduler.enqueue(queued("high-2", 5));
+        scheduler.enqueue(queued("urgent", 9));
+
+        assert_eq!(started(&mut scheduler), ["urgent", "high", "high-2", "low"]);
+        assert_eq!(scheduler.position("low-2"), Some(1));
+    }
+
+    #[test]
+    fn positions_count_from_one_and_follow_dequeues() {
+        let mut scheduler = scheduler(4, 1, 10);
+        for job_id in ["a", "b", "c"] {
+            scheduler.enqueue(queued(job_id, 0));
+        }
+        assert_eq!(scheduler.position("a"), Some(1));
+        assert_eq!(scheduler.position("c"), Some(3));
+
+        assert!(scheduler.dequeue("b").is_some());
+        assert!(scheduler.dequeue("b").is_none());
+        assert_eq!(scheduler.position("c"), Some(2));
+
+        assert_eq!(started(&mut scheduler), ["a"]);
+        assert_eq!(scheduler.position("a"), None);
+        assert_eq!(scheduler.position("c"), Some(1));
+    }
+
+    #[test]
+    fn full_once_max_queued_jobs_wait() {
+        let mut scheduler = scheduler(4, 1, 2);
+        scheduler.enqueue(queued("a", 0));
+        scheduler.enqueue(queued("b", 0));
+        assert!(scheduler.is_full());
+
+        // Running jobs don't count against the queue
+        started(&mut scheduler);
+        assert!(!scheduler.is_full());
+        assert_eq!(scheduler.max_queued(), 2);
+    }
+
+    #[test]
+    fn cores_are_shared_between_running_jobs() {
+        let mut scheduler = scheduler(7, 3, 10);
+        scheduler.enqueue(queued("a", 0));
+        started(&mut scheduler);
+        assert_eq!(scheduler.workers("a"), Some(7));
+
+        scheduler.enqueue(queued("b", 0));
+        scheduler.enqueue(queued("c", 0));
+        started(&mut scheduler);
+        let mut shares: Vec<usize> = ["a", "b", "c"]
+            .iter()
+            .filter_map(|job_id| scheduler.workers(job_id))
+            .collect();
+        shares.sort_unstable();
+        assert_eq!(shares, [2, 2, 3]);
+
+        scheduler.finish("a");
+        scheduler.finish("b");
+        assert_eq!(scheduler.workers("a"), None);
+        assert_eq!(scheduler.workers("c"), Some(7));
+    }
+
+    #[test]
+    fn jobs_keep_a_worker_when_cores_run_short() {
+        let mut scheduler = scheduler(1, 2, 10);
+        scheduler.enqueue(queued("a", 0));
+        scheduler.enqueue(queued("b", 0));
+        started(&mut scheduler);
+        assert_eq!(scheduler.workers("a"), Some(1));
+        assert_eq!(scheduler.workers("b"), Some(1));
+    }
+
+    #[test]
+    fn search_pools_are_sized_from_the_configured_threads() {
+        let mut scheduler = scheduler(12, 2, 10);
+        scheduler.enqueue(queued("a", 0));
+        let (_, limit) = scheduler.start_next().remove(0);
+        assert_eq!(limit.capacity(), 12);
+        assert_eq!(limit.get(), 12);
+    }
+}
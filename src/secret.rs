@@ -0,0 +1,114 @@
+//! Text that must not outlive its use or show up in logs.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use zeroize::Zeroize;
+
+/// A private key or seed phrase.
+///
+/// The text is wiped from memory when the value is dropped and never
+/// printed by `Debug`. It has no `Display`; reading it takes an explicit
+/// [`SecretString::expose`]. Serializing writes the text itself, since
+/// results have to reach the client somehow.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// The secret text, for showing it to its owner or writing it out.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{VanityMatch, VanityResult};
+    use solana_sdk::signature::{Keypair, Signer};
+
+    const PHRASE: &str =
+        "pill tomorrow foster begin walnut borrow virtual kick shift mutual shoe scatter";
+
+    // Every way the secret key of `keypair` could be spelled out
+    fn spellings(keypair: &Keypair) -> Vec<String> {
+        let bytes = keypair.to_bytes();
+        vec![
+            bs58::encode(bytes).into_string(),
+            bs58::encode(&bytes[..32]).into_string(),
+            format!("{:?}", &bytes[..32]),
+            format!("{:?}", &bytes[..]),
+        ]
+    }
+
+    #[test]
+    fn debug_is_redacted() {
+        let secret = SecretString::new("hunter2".to_string());
+        assert_eq!(format!("{:?}", secret), "SecretString(<redacted>)");
+        assert_eq!(
+            format!("{:?}", Some(secret)),
+            "Some(SecretString(<redacted>))"
+        );
+    }
+
+    #[test]
+    fn results_debug_without_secret_material() {
+        let keypair = Keypair::new();
+        let found = VanityMatch {
+            address: keypair.pubkey(),
+            keypair: Some(keypair.insecure_clone()),
+            mnemonic: Some(PHRASE.to_string().into()),
+            program_address: None,
+            derived_seed: None,
+            owner: None,
+            offset: None,
+        };
+        let result = VanityResult::from(&found);
+        assert!(!result.private_key.is_empty());
+
+        for debug in [format!("{:?}", found), format!("{:#?}", result)] {
+            assert!(debug.contains(&keypair.pubkey().to_string()));
+            assert!(!debug.contains("walnut"), "{}", debug);
+            for spelling in spellings(&keypair) {
+                assert!(!debug.contains(&spelling), "{}", debug);
+            }
+        }
+    }
+}
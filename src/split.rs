@@ -0,0 +1,204 @@
+//! Split-key vanity generation, for searches run on someone else's machine.
+//!
+//! The client keeps a secret scalar `a` and hands out only its public point
+//! `A = a·B`. The searcher looks for an offset `b` such that `A + b·B`
+//! matches the pattern, and the client adds the offset to its secret:
+//! `a + b` is the private scalar of the address. The offset says nothing
+//! about `a`, so whoever runs the search never learns the key.
+//!
+//! The combined key is a scalar rather than a 32-byte seed, because seeds
+//! are hashed into scalars and hashes can't be added. It signs like any
+//! ed25519 key, but wallets and `solana-keygen` only import seeds, so it is
+//! written out as a 64-byte expanded secret key: the scalar followed by the
+//! nonce prefix used for signing.
+
+use curve25519_dalek::constants::{ED25519_BASEPOINT_POINT, ED25519_BASEPOINT_TABLE};
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::{ExpandedSecretKey, PublicKey};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Search for an offset to someone else's public point instead of a keypair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitKeyOptions {
+    /// Base58 public point of the client's [`SplitKey`].
+    pub public_point: String,
+}
+
+impl SplitKeyOptions {
+    pub fn new(public_point: impl Into<String>) -> Self {
+        Self {
+            public_point: public_point.into(),
+        }
+    }
+
+    pub fn point(&self) -> Result<EdwardsPoint, String> {
+        let invalid = || format!("Invalid split key point '{}'", self.public_point);
+        let bytes: [u8; 32] = bs58::decode(&self.public_point)
+            .into_vec()
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(invalid)?;
+        CompressedEdwardsY(bytes)
+            .decompress()
+            // Points outside the prime-order subgroup don't come from a scalar
+            // times the base point and can't be combined into a usable key
+            .filter(|point| !point.is_small_order() && point.is_torsion_free())
+            .ok_or_else(invalid)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.point().map(|_| ())
+    }
+}
+
+/// The client's half of a split-key search. Only its public point leaves
+/// the client.
+pub struct SplitKey {
+    scalar: Scalar,
+    nonce: [u8; 32],
+}
+
+impl SplitKey {
+    /// Draws a fresh secret from operating system randomness.
+    pub fn generate() -> Self {
+        let mut wide = Zeroizing::new([0u8; 64]);
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(wide.as_mut());
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            scalar: Scalar::from_bytes_mod_order_wide(&wide),
+            nonce,
+        }
+    }
+
+    /// Base58 point to hand to the searcher.
+    pub fn public_point(&self) -> String {
+        let point = &self.scalar * &ED25519_BASEPOINT_TABLE;
+        bs58::encode(point.compress().as_bytes()).into_string()
+    }
+
+    /// The secret scalar followed by the nonce prefix.
+    pub fn to_bytes(&self) -> Zeroizing<[u8; 64]> {
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        bytes[..32].copy_from_slice(self.scalar.as_bytes());
+        bytes[32..].copy_from_slice(&self.nonce);
+        bytes
+    }
+
+    /// Reads a secret written by [`SplitKey::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let invalid = || "Split key secret must be 64 bytes".to_string();
+        let bytes: Zeroizing<[u8; 64]> = Zeroizing::new(bytes.try_into().map_err(|_| invalid())?);
+        let scalar = Scalar::from_canonical_bytes(bytes[..32].try_into().unwrap())
+            .ok_or_else(|| "Split key secret holds an invalid scalar".to_string())?;
+        Ok(Self {
+            scalar,
+            nonce: bytes[32..].try_into().unwrap(),
+        })
+    }
+
+    /// Adds the hex `offset` a search found to this secret, giving the key of
+    /// the matching address.
+    pub fn combine(&self, offset: &str) -> Result<CombinedKey, String> {
+        let offset = parse_offset(offset)?;
+        let scalar = self.scalar + offset;
+        let address = (&scalar * &ED25519_BASEPOINT_TABLE).compress().to_bytes();
+
+        let mut bytes = Zeroizing::new([0u8; 64]);
+        bytes[..32].copy_from_slice(scalar.as_bytes());
+        bytes[32..].copy_from_slice(&self.nonce);
+        Ok(CombinedKey {
+            address: Pubkey::new_from_array(address),
+            secret: ExpandedSecretKey::from_bytes(bytes.as_ref()).expect("64 bytes"),
+        })
+    }
+}
+
+impl Drop for SplitKey {
+    fn drop(&mut self) {
+        self.scalar.zeroize();
+        self.nonce.zeroize();
+    }
+}
+
+/// The private key of a split-key search's address.
+pub struct CombinedKey {
+    address: Pubkey,
+    secret: ExpandedSecretKey,
+}
+
+impl CombinedKey {
+    pub fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    /// The 64-byte expanded secret key: scalar, then nonce prefix.
+    pub fn to_bytes(&self) -> Zeroizing<[u8; 64]> {
+        Zeroizing::new(self.secret.to_bytes())
+    }
+
+    /// Signs `message` as the address would, e.g. a serialized transaction
+    /// message.
+    pub fn sign_message(&self, message: &[u8]) -> Signature {
+        let public = PublicKey::from_bytes(self.address.as_ref()).expect("address is on the curve");
+        Signature::from(self.secret.sign(message, &public).to_bytes())
+    }
+}
+
+/// Reads a hex offset as a search result carries it.
+fn parse_offset(offset: &str) -> Result<Scalar, String> {
+    let invalid = || format!("Invalid split key offset '{}'", offset);
+    let offset = offset.trim();
+    if offset.len() != 64 || !offset.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&offset[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Scalar::from_canonical_bytes(bytes).ok_or_else(invalid)
+}
+
+/// Per-thread walk along `A + b·B` for growing offsets `b`.
+pub struct SplitKeyStream {
+    start: Scalar,
+    point: EdwardsPoint,
+    steps: u64,
+}
+
+impl SplitKeyStream {
+    /// Starts at a random offset, so workers don't retrace each other's
+    /// points. The options must be valid.
+    pub fn new(options: &SplitKeyOptions) -> Self {
+        let mut wide = [0u8; 64];
+        OsRng.fill_bytes(&mut wide);
+        let start = Scalar::from_bytes_mod_order_wide(&wide);
+        let point = options.point().expect("split key options are validated");
+        Self {
+            start,
+            point: point + &start * &ED25519_BASEPOINT_TABLE,
+            steps: 0,
+        }
+    }
+
+    /// Returns the next candidate's address bytes and how many steps in it
+    /// lies. Each step is one point addition instead of a scalar
+    /// multiplication.
+    pub fn next_candidate(&mut self) -> ([u8; 32], u64) {
+        let steps = self.steps;
+        let address = self.point.compress().to_bytes();
+        self.point += ED25519_BASEPOINT_POINT;
+        self.steps += 1;
+        (address, steps)
+    }
+
+    /// The offset of the candidate `steps` in, as little-endian scalar bytes.
+    pub fn offset(&self, steps: u64) -> [u8; 32] {
+        (self.start + Scalar::from(steps)).to_bytes()
+    }
+}
@@ -0,0 +1,183 @@
+//! Vanity associated token accounts.
+//!
+//! A wallet's token account for a mint lives at a program address derived
+//! from the wallet, the token program and the mint. Searching wallets until
+//! that address matches gives, say, a recognizable USDC account, owned by an
+//! ordinary keypair.
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::fmt;
+use std::str::FromStr;
+
+/// The associated token account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+/// The classic SPL token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// The Token-2022 program.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Match a wallet's associated token account instead of the wallet itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenAccountOptions {
+    /// Base58 address of the token mint.
+    pub mint: String,
+    #[serde(default)]
+    pub token_program: TokenProgram,
+}
+
+/// Which token program the mint belongs to.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenProgram {
+    /// The classic SPL token program.
+    #[default]
+    Token,
+    /// The Token-2022 program.
+    #[serde(rename = "token-2022")]
+    Token2022,
+}
+
+impl TokenAccountOptions {
+    pub fn new(mint: impl Into<String>) -> Self {
+        Self {
+            mint: mint.into(),
+            token_program: TokenProgram::Token,
+        }
+    }
+
+    pub fn with_token_program(mut self, token_program: TokenProgram) -> Self {
+        self.token_program = token_program;
+        self
+    }
+
+    pub fn mint(&self) -> Result<Pubkey, String> {
+        Pubkey::from_str(&self.mint).map_err(|_| format!("Invalid mint '{}'", self.mint))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.mint().map(|_| ())
+    }
+
+    /// The associated token account `wallet` has for this mint.
+    /// The options must be valid.
+    pub fn token_account(&self, wallet: &Pubkey) -> Pubkey {
+        let mint = self.mint().expect("token account options are validated");
+        associated_token_address(wallet, &mint, &self.token_program.id())
+    }
+}
+
+impl TokenProgram {
+    pub fn id(&self) -> Pubkey {
+        match self {
+            TokenProgram::Token => TOKEN_PROGRAM_ID,
+            TokenProgram::Token2022 => TOKEN_2022_PROGRAM_ID,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenProgram::Token => "token",
+            TokenProgram::Token2022 => "token-2022",
+        }
+    }
+}
+
+impl fmt::Display for TokenProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TokenProgram {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "token" => Ok(TokenProgram::Token),
+            "token-2022" | "token2022" => Ok(TokenProgram::Token2022),
+            _ => Err("Token program must be 'token' or 'token-2022'".to_string()),
+        }
+    }
+}
+
+/// Same address as `spl_associated_token_account::get_associated_token_address_with_program_id`.
+pub fn associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Per-thread mapping from candidate wallets to their token accounts.
+pub struct TokenAccountStream {
+    mint: Pubkey,
+    token_program: Pubkey,
+}
+
+impl TokenAccountStream {
+    /// The options must be valid.
+    pub fn new(options: &TokenAccountOptions) -> Self {
+        Self {
+            mint: options.mint().expect("token account options are validated"),
+            token_program: options.token_program.id(),
+        }
+    }
+
+    /// Address bytes of the token account the wallet `wallet` has.
+    pub fn token_account(&self, wallet: [u8; 32]) -> [u8; 32] {
+        let wallet = Pubkey::new_from_array(wallet);
+        associated_token_address(&wallet, &self.mint, &self.token_program).to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLET: Pubkey = pubkey!("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const PYUSD: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+
+    #[test]
+    fn token_accounts_match_the_associated_token_program() {
+        let cases = [
+            (
+                USDC,
+                TokenProgram::Token,
+                "FGETo8T8wMcN2wCjav8VK6eh3dLk63evNDPxzLSJra8B",
+            ),
+            (
+                PYUSD,
+                TokenProgram::Token2022,
+                "897krAvWH3RbymaCYE3o9emopUwocieHuKTUk9nySpq6",
+            ),
+            // The program is part of the seeds, so the same mint moves
+            (
+                USDC,
+                TokenProgram::Token2022,
+                "GdjpegrtGwU3pgtzPivYVViSA8rmGL248qBVKzsrU3DD",
+            ),
+        ];
+        for (mint, token_program, account) in cases {
+            let options = TokenAccountOptions::new(mint).with_token_program(token_program);
+            assert_eq!(options.token_account(&WALLET).to_string(), account);
+
+            let stream = TokenAccountStream::new(&options);
+            let bytes = stream.token_account(WALLET.to_bytes());
+            assert_eq!(Pubkey::new_from_array(bytes).to_string(), account);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_mints() {
+        assert!(TokenAccountOptions::new(USDC).validate().is_ok());
+        assert_eq!(
+            TokenAccountOptions::new("usdc").validate().unwrap_err(),
+            "Invalid mint 'usdc'"
+        );
+    }
+}
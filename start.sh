@@ -43,8 +43,8 @@ echo "====================================================================="
 echo ""
 echo "To generate a vanity address, run one of these commands:"
 echo ""
-echo "  ./run_cli.sh abc prefix  # Generate address starting with 'abc'"
-echo "  ./run_cli.sh xyz suffix  # Generate address ending with 'xyz'"
+echo "  ./run_cli.sh abc prefix --server http://127.0.0.1:3001  # Generate address starting with 'abc'"
+echo "  ./run_cli.sh xyz suffix --server http://127.0.0.1:3001  # Generate address ending with 'xyz'"
 echo ""
 echo "Replace 'abc' or 'xyz' with your desired 3-8 character pattern."
 echo ""
@@ -0,0 +1,89 @@
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::scalar::Scalar;
+use solana_sdk::signature::Signer;
+use solana_vanity_generator::{
+    find_vanity_addresses, KeyFormat, SplitKey, SplitKeyOptions, VanityMatch, VanityPosition,
+    VanityRequest, VanityResult,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64};
+
+// Runs a split-key search for `key`'s public point, as a server would
+fn search(key: &SplitKey) -> VanityMatch {
+    let request = VanityRequest::new("a", VanityPosition::Prefix)
+        .with_split_key(SplitKeyOptions::new(key.public_point()));
+    find_vanity_addresses(
+        &request,
+        &AtomicBool::new(false),
+        &AtomicU64::new(0),
+        |_| {},
+    )
+    .unwrap()
+    .remove(0)
+}
+
+#[test]
+fn combined_key_signs_for_the_found_address() {
+    let key = SplitKey::generate();
+    let result = VanityResult::from(&search(&key));
+    assert!(result.public_key.to_lowercase().starts_with('a'));
+
+    let combined = key.combine(result.offset.as_ref().unwrap()).unwrap();
+    assert_eq!(combined.address().to_string(), result.public_key);
+
+    let message = b"split-key transfer";
+    let signature = combined.sign_message(message);
+    assert!(signature.verify(combined.address().as_ref(), message));
+}
+
+#[test]
+fn search_result_carries_no_private_key() {
+    let key = SplitKey::generate();
+    let found = search(&key);
+    assert!(found.keypair.is_none());
+
+    let result = VanityResult::from(&found);
+    assert!(result.private_key.is_empty());
+    let json = serde_json::to_string(&result).unwrap();
+    let combined = key.combine(result.offset.as_ref().unwrap()).unwrap();
+    assert!(!json.contains(&KeyFormat::Hex.encode(&combined.to_bytes()[..32])));
+    assert!(!json.contains(&KeyFormat::Hex.encode(&key.to_bytes()[..32])));
+}
+
+#[test]
+fn offset_alone_does_not_give_the_key() {
+    let key = SplitKey::generate();
+    let found = search(&key);
+    let offset = found.offset.unwrap();
+
+    // Neither as a seed nor as a scalar does the offset lead to the address
+    let as_scalar = &Scalar::from_bytes_mod_order(offset) * &ED25519_BASEPOINT_TABLE;
+    assert_ne!(as_scalar.compress().to_bytes(), found.address.to_bytes());
+    let keypair = solana_sdk::signer::keypair::keypair_from_seed(&offset).unwrap();
+    assert_ne!(keypair.pubkey(), found.address);
+
+    // Someone else's secret combined with the offset gives another address
+    let other = SplitKey::generate()
+        .combine(&KeyFormat::Hex.encode(&offset))
+        .unwrap();
+    assert_ne!(other.address(), found.address);
+}
+
+#[test]
+fn secret_round_trips_through_bytes() {
+    let key = SplitKey::generate();
+    let restored = SplitKey::from_bytes(key.to_bytes().as_ref()).unwrap();
+    assert_eq!(restored.public_point(), key.public_point());
+    assert!(SplitKey::from_bytes(&[0; 32]).is_err());
+}
+
+#[test]
+fn rejects_points_that_cannot_be_combined() {
+    // All-zero bytes decode to a point of order 4
+    assert!(SplitKeyOptions::new("11111111111111111111111111111111")
+        .validate()
+        .is_err());
+    assert!(SplitKeyOptions::new("not-a-point").validate().is_err());
+    assert!(SplitKeyOptions::new(SplitKey::generate().public_point())
+        .validate()
+        .is_ok());
+}